@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+use crate::diversity::DiversityStats;
+use crate::island::IslandSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AdaptivePolicy {
+    pub stagnation_limit: usize,
+    pub min_diversity: f64,
+    pub mutation_factor: f64,
+    pub max_mutation_rate: f64,
+}
+
+impl Default for AdaptivePolicy {
+    fn default() -> Self {
+        Self { stagnation_limit: 0, min_diversity: 0.25, mutation_factor: 1.5, max_mutation_rate: 0.5 }
+    }
+}
+
+impl AdaptivePolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.stagnation_limit > 0
+    }
+
+    pub fn adapt(&self, settings: IslandSettings, stagnant_generations: usize, diversity: &DiversityStats, max_elite_count: usize) -> IslandSettings {
+        if !self.is_enabled() {
+            return settings;
+        }
+
+        let stagnating = stagnant_generations >= self.stagnation_limit;
+        let collapsing = diversity.adjacency_diversity < self.min_diversity;
+        let improving = stagnant_generations == 0;
+
+        if stagnating || collapsing {
+            let mutation_rate = (settings.mutation_rate * self.mutation_factor).max(MIN_RAISED_MUTATION_RATE);
+            let elite_count = if settings.elite_count > 1 { settings.elite_count - 1 } else { settings.elite_count };
+
+            IslandSettings {
+                selection: settings.selection.with_adjusted_pressure(-1),
+                mutation_rate: mutation_rate.min(self.max_mutation_rate),
+                elite_count,
+            }
+        } else if improving {
+            let mutation_rate = settings.mutation_rate / self.mutation_factor;
+
+            IslandSettings {
+                selection: settings.selection.with_adjusted_pressure(1),
+                mutation_rate: if mutation_rate < MIN_RAISED_MUTATION_RATE / 2.0 { 0.0 } else { mutation_rate },
+                elite_count: (settings.elite_count + 1).min(max_elite_count),
+            }
+        } else {
+            settings
+        }
+    }
+}
+
+const MIN_RAISED_MUTATION_RATE: f64 = 0.02;
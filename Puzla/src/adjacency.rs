@@ -1,52 +1,58 @@
 use std::collections::HashMap;
 use std::vec::Vec;
 
-use image::DynamicImage;
-
-use crate::dissimilarity::calculate_dissimilarity;
+use crate::dissimilarity::DissimilarityMatrix;
 use rayon::prelude::*;
 
+/// For every piece and relation ('L', 'R', 'U', 'D'), the other pieces
+/// ordered from most to least compatible, plus the best buddy pairs: pieces
+/// that are each other's most compatible neighbour in opposite relations.
 pub struct AdjacencyData {
-    pieces: HashMap<String, DynamicImage>,
     compatibilities: HashMap<String, HashMap<char, Vec<(f64, String)>>>,
     best_buddies: Vec<(String, String, char)>
 }
 
 impl AdjacencyData {
-    pub fn new(pieces: &HashMap<String, DynamicImage>) -> Self {
+    /// Ranks every pair of pieces by their right and down dissimilarities.
+    pub fn new(piece_keys: &[String], dissimilarity_r: &DissimilarityMatrix, dissimilarity_d: &DissimilarityMatrix) -> Self {
         let mut compatibilities = HashMap::new();
-        for (key, _) in pieces {
+        for key in piece_keys {
             compatibilities.insert(key.clone(), HashMap::new());
         }
         let best_buddies = Vec::new();     
 
-        let mut instance = Self { pieces: pieces.clone(), compatibilities, best_buddies };
+        let mut instance = Self { compatibilities, best_buddies };
 
-        instance.calculate_compatibilities();
+        instance.calculate_compatibilities(piece_keys, dissimilarity_r, dissimilarity_d);
         instance.generate_best_buddies();
 
         instance
     }
 
-    fn calculate_compatibilities(&mut self) {
-        for (key1, piece1) in &self.pieces {
+    fn calculate_compatibilities(&mut self, piece_keys: &[String], dissimilarity_r: &DissimilarityMatrix, dissimilarity_d: &DissimilarityMatrix) {
+        for key1 in piece_keys {
             for &relation in &['L', 'R', 'U', 'D'] {
                 let mut dissimilarities: Vec<(f64, String)> = Vec::new();
     
-                for (key2, piece2) in &self.pieces {
+                for key2 in piece_keys {
                     if key1 != key2 {
-                        let dissimilarity = calculate_dissimilarity(&piece1, &piece2, relation);
+                        let dissimilarity = match relation {
+                            'R' => dissimilarity_r[&(key1.clone(), key2.clone())],
+                            'L' => dissimilarity_r[&(key2.clone(), key1.clone())],
+                            'D' => dissimilarity_d[&(key1.clone(), key2.clone())],
+                            _ => dissimilarity_d[&(key2.clone(), key1.clone())],
+                        };
                         dissimilarities.push((dissimilarity, key2.clone()));
                     }
                 }
                 
-                dissimilarities.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                dissimilarities.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
     
                 let top_pieces: Vec<(f64, String)> = dissimilarities.iter().take(100).cloned().collect();
     
                 self.compatibilities
                     .entry(key1.clone())
-                    .or_insert_with(HashMap::new)
+                    .or_default()
                     .insert(relation, top_pieces);
             }
         }
@@ -86,6 +92,7 @@ impl AdjacencyData {
 
     
 
+    /// Returns the best buddy of `piece` in `relation`, if it has one.
     pub fn get_best_buddy_in_relation(&self, piece: &String, relation: char) -> Option<String> {
         self.best_buddies.iter().find_map(|(buddy1, buddy2, rel)| {
             if *rel == relation && buddy1 == piece {
@@ -93,9 +100,10 @@ impl AdjacencyData {
             } else {
                 None
             }
-        }).map(|x| x).cloned()
+        }).cloned()
     }
 
+    /// Returns the pieces that can sit in `relation` to `piece`, most compatible first.
     pub fn get_most_compatible(&self, piece: &str, relation: char) -> &Vec<(f64, String)> {
         let piece_map = self.compatibilities.get(piece).unwrap();
         piece_map.get(&relation).unwrap()
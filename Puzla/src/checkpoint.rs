@@ -0,0 +1,159 @@
+use std::fs;
+use std::time::Duration;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::diversity::StagnationTracker;
+use crate::island::{Island, IslandSettings};
+use crate::termination::TerminationState;
+
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub config: Config,
+    pub seed: u64,
+    /// Grid size as (width, height) the populations were evolved on.
+    #[serde(default)]
+    pub grid_size: Option<(usize, usize)>,
+    pub generation: usize,
+    pub elapsed: Duration,
+    pub best_fitness: Option<f64>,
+    pub stagnant_generations: usize,
+    pub islands: Vec<IslandState>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IslandState {
+    pub id: usize,
+    pub population: Vec<Vec<Vec<String>>>,
+    pub settings: IslandSettings,
+    pub stagnation: StagnationTracker,
+    pub rng: RngState,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: u128,
+}
+
+impl RngState {
+    pub fn capture(rng: &ChaCha8Rng) -> Self {
+        Self { seed: rng.get_seed(), stream: rng.get_stream(), word_pos: rng.get_word_pos() }
+    }
+
+    pub fn restore(&self) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::from_seed(self.seed);
+        rng.set_stream(self.stream);
+        rng.set_word_pos(self.word_pos);
+        rng
+    }
+}
+
+impl Checkpoint {
+    pub fn capture(config: &Config, seed: u64, grid_size: (usize, usize), generation: usize, termination: &TerminationState, islands: &[Island]) -> Self {
+        Self {
+            config: config.clone(),
+            seed,
+            grid_size: Some(grid_size),
+            generation,
+            elapsed: termination.elapsed(),
+            best_fitness: termination.best_fitness(),
+            stagnant_generations: termination.stagnant_generations(),
+            islands: islands.iter().map(|island| island.to_state()).collect(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = serde_json::to_string(self).map_err(|error| format!("Failed to serialize checkpoint: {}", error))?;
+
+        let temporary_path = format!("{}.tmp", path);
+        fs::write(&temporary_path, contents).map_err(|error| format!("Failed to write checkpoint {}: {}", temporary_path, error))?;
+        fs::rename(&temporary_path, path).map_err(|error| format!("Failed to write checkpoint {}: {}", path, error))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|error| format!("Failed to read checkpoint {}: {}", path, error))?;
+        serde_json::from_str(&contents).map_err(|error| format!("Failed to parse checkpoint {}: {}", path, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::problem::{CostMatrixProblem, PreparedProblem};
+    use crate::solver::Solver;
+    use crate::termination::TerminationCriteria;
+    use crate::utils;
+
+    // Eleven pieces on a 4 x 3 grid, so one cell stays empty.
+    fn problem() -> PreparedProblem {
+        let piece_keys: Vec<String> = (0..11).map(|i| format!("piece{:02}", i)).collect();
+        let mut right = HashMap::new();
+        let mut down = HashMap::new();
+        for (i, first) in piece_keys.iter().enumerate() {
+            for (j, second) in piece_keys.iter().enumerate() {
+                if i != j {
+                    right.insert((first.clone(), second.clone()), ((i * 7 + j * 13) % 17) as f64 + 0.1 * i as f64);
+                    down.insert((first.clone(), second.clone()), ((i * 11 + j * 5) % 19) as f64 + 0.01 * j as f64);
+                }
+            }
+        }
+        PreparedProblem::new(&CostMatrixProblem { piece_keys, width: 4, height: 3, right, down, missing_cost: 100.0 }).unwrap()
+    }
+
+    fn config() -> Config {
+        Config {
+            population_size: 40,
+            islands: 2,
+            migration_interval: 3,
+            mutation_rate: 0.1,
+            seed: Some(5),
+            termination: TerminationCriteria { max_generations: Some(12), ..TerminationCriteria::default() },
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn resuming_a_checkpoint_matches_an_uninterrupted_run() {
+        let problem = problem();
+        let uninterrupted = Solver::builder(&problem).config(config()).build().unwrap().run();
+
+        let mut interrupted = Solver::builder(&problem).config(config()).build().unwrap();
+        for _ in 0..5 {
+            assert!(interrupted.step().is_none());
+        }
+        let saved = serde_json::to_string(&interrupted.checkpoint()).unwrap();
+        let checkpoint: Checkpoint = serde_json::from_str(&saved).unwrap();
+
+        let resumed = Solver::builder(&problem).config(checkpoint.config.clone()).resume(checkpoint).build().unwrap().run();
+
+        assert_eq!(resumed.best, uninterrupted.best);
+        assert_eq!(resumed.stats.best_fitness.to_bits(), uninterrupted.stats.best_fitness.to_bits());
+        assert_eq!(resumed.stats.generation, 12);
+    }
+
+    #[test]
+    fn resuming_keeps_the_checkpointed_grid_size() {
+        let problem = problem();
+        let solver = Solver::builder(&problem).config(config()).grid_size(3, 4).build().unwrap();
+        let checkpoint = || serde_json::from_str::<Checkpoint>(&serde_json::to_string(&solver.checkpoint()).unwrap()).unwrap();
+
+        let resumed = Solver::builder(&problem).config(config()).resume(checkpoint()).build().unwrap();
+        assert!(resumed.islands().iter().flat_map(|island| &island.population).all(|chromosome| utils::matrix_size(chromosome) == (4, 3)));
+
+        assert!(Solver::builder(&problem).config(config()).grid_size(4, 3).resume(checkpoint()).build().is_err());
+    }
+
+    #[test]
+    fn empty_grids_are_rejected() {
+        let problem = problem();
+        assert!(Solver::builder(&problem).config(config()).grid_size(0, 3).build().is_err());
+        assert!(Solver::builder(&problem).config(config()).grid_size(4, 0).build().is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+use serde::{Deserialize, Serialize};
+
+// D65 reference white.
+const WHITE_X: f64 = 0.95047;
+const WHITE_Y: f64 = 1.0;
+const WHITE_Z: f64 = 1.08883;
+const EPSILON: f64 = 216.0 / 24389.0;
+const KAPPA: f64 = 24389.0 / 27.0;
+// Scale between 8 and 16 bit channels, 65535 / 255.
+const CHANNEL_SCALE: f64 = 257.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ColorSpace {
+    Rgb,
+    Lab,
+}
+
+impl ColorSpace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rgb" => Some(ColorSpace::Rgb),
+            "lab" => Some(ColorSpace::Lab),
+            _ => None,
+        }
+    }
+
+    /// Converts an sRGB piece into this color space. Lab is stored in 16 bit
+    /// channels as L, a + 128 and b + 128, all scaled by the same factor, so
+    /// [`channels`] reads it back in Lab units with sub-unit precision.
+    pub fn convert(&self, piece: &DynamicImage) -> DynamicImage {
+        match self {
+            ColorSpace::Rgb => piece.clone(),
+            ColorSpace::Lab => {
+                let rgba = piece.to_rgba8();
+                let lab = ImageBuffer::from_fn(rgba.width(), rgba.height(), |x, y| {
+                    let pixel = rgba.get_pixel(x, y);
+                    let (l, a, b) = srgb_to_lab(pixel[0], pixel[1], pixel[2]);
+                    let encode = |value: f64| (value * CHANNEL_SCALE).round().clamp(0.0, f64::from(u16::MAX)) as u16;
+                    Rgba([encode(l), encode(a + 128.0), encode(b + 128.0), u16::from(pixel[3]) * CHANNEL_SCALE as u16])
+                });
+                DynamicImage::ImageRgba16(lab)
+            }
+        }
+    }
+}
+
+/// The first three channels of a pixel on the 8 bit scale. 16 bit pieces,
+/// such as converted Lab pieces, keep their fractional part.
+pub fn channels(piece: &DynamicImage, x: u32, y: u32) -> [f64; 3] {
+    match piece {
+        DynamicImage::ImageRgba16(image) => {
+            let pixel = image.get_pixel(x, y);
+            [0, 1, 2].map(|channel| f64::from(pixel[channel]) / CHANNEL_SCALE)
+        }
+        _ => {
+            let pixel = piece.get_pixel(x, y);
+            [f64::from(pixel[0]), f64::from(pixel[1]), f64::from(pixel[2])]
+        }
+    }
+}
+
+fn srgb_to_lab(red: u8, green: u8, blue: u8) -> (f64, f64, f64) {
+    let linear = |channel: u8| {
+        let value = f64::from(channel) / 255.0;
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(red), linear(green), linear(blue));
+
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / WHITE_X;
+    let y = (0.2126729 * r + 0.7151522 * g + 0.0721750 * b) / WHITE_Y;
+    let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / WHITE_Z;
+
+    let f = |t: f64| if t > EPSILON { t.cbrt() } else { (KAPPA * t + 16.0) / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbImage;
+
+    use super::*;
+
+    fn assert_lab(actual: (f64, f64, f64), expected: (f64, f64, f64), tolerance: f64) {
+        let close = (actual.0 - expected.0).abs() < tolerance && (actual.1 - expected.1).abs() < tolerance && (actual.2 - expected.2).abs() < tolerance;
+        assert!(close, "{:?} is not close to {:?}", actual, expected);
+    }
+
+    #[test]
+    fn srgb_to_lab_matches_reference_values() {
+        assert_lab(srgb_to_lab(255, 255, 255), (100.0, 0.0, 0.0), 1e-3);
+        assert_lab(srgb_to_lab(0, 0, 0), (0.0, 0.0, 0.0), 1e-9);
+        assert_lab(srgb_to_lab(255, 0, 0), (53.2408, 80.0925, 67.2032), 1e-2);
+        assert_lab(srgb_to_lab(0, 0, 255), (32.2970, 79.1875, -107.8602), 1e-2);
+    }
+
+    #[test]
+    fn lab_pieces_keep_the_same_scale_and_fractions_on_every_channel() {
+        let colors = [[255, 255, 255], [255, 0, 0], [12, 200, 99]];
+        let piece = DynamicImage::ImageRgb8(RgbImage::from_fn(colors.len() as u32, 1, |x, _| image::Rgb(colors[x as usize])));
+        let lab = ColorSpace::Lab.convert(&piece);
+
+        for (x, color) in colors.iter().enumerate() {
+            let (l, a, b) = srgb_to_lab(color[0], color[1], color[2]);
+            let [read_l, read_a, read_b] = channels(&lab, x as u32, 0);
+            assert_lab((read_l, read_a - 128.0, read_b - 128.0), (l, a, b), 0.5 / CHANNEL_SCALE + 1e-9);
+        }
+    }
+}
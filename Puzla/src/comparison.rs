@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use image::{imageops, DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::ground_truth::GroundTruth;
+use crate::observer::Observer;
+use crate::stats::GenerationStats;
+use crate::termination::StopReason;
+use crate::utils;
+
+const SIDE_BY_SIDE_GAP: u32 = 8;
+const DIFFERENCE_GAIN: u32 = 3;
+const OUTLINE_WIDTH: u32 = 2;
+const OUTLINE_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+pub fn render_side_by_side(solved: &RgbaImage, reference: &DynamicImage) -> RgbaImage {
+    let reference = reference_at_size(reference, solved.width(), solved.height());
+    let mut combined = RgbaImage::from_pixel(2 * solved.width() + SIDE_BY_SIDE_GAP, solved.height(), Rgba([255, 255, 255, 255]));
+
+    imageops::replace(&mut combined, solved, 0, 0);
+    imageops::replace(&mut combined, &reference, solved.width() + SIDE_BY_SIDE_GAP, 0);
+
+    combined
+}
+
+pub fn render_difference(solved: &RgbaImage, reference: &DynamicImage, misplaced: &[(usize, usize)], pitch: (u32, u32)) -> RgbaImage {
+    let reference = reference_at_size(reference, solved.width(), solved.height());
+
+    let mut difference = RgbaImage::from_fn(solved.width(), solved.height(), |x, y| {
+        let (solved_pixel, reference_pixel) = (solved.get_pixel(x, y), reference.get_pixel(x, y));
+        let channel = |index: usize| {
+            let delta = (solved_pixel[index] as i32 - reference_pixel[index] as i32).unsigned_abs();
+            (delta * DIFFERENCE_GAIN).min(255) as u8
+        };
+        Rgba([channel(0), channel(1), channel(2), 255])
+    });
+
+    for &(row, col) in misplaced {
+        outline_cell(&mut difference, col as u32 * pitch.0, row as u32 * pitch.1, pitch.0, pitch.1);
+    }
+
+    difference
+}
+
+pub struct ReferenceComparison<'a> {
+    pub pieces: &'a HashMap<String, DynamicImage>,
+    pub pitch: (u32, u32),
+    pub reference: &'a DynamicImage,
+    pub ground_truth: &'a GroundTruth,
+    pub side_by_side: Option<String>,
+    pub difference: Option<String>,
+}
+
+impl Observer for ReferenceComparison<'_> {
+    fn on_termination(&mut self, _reason: &StopReason, _stats: &GenerationStats, best: &[Vec<String>]) {
+        let solved = utils::create_image_from_matrix(&utils::create_image_matrix(best, self.pieces, self.pitch));
+
+        if let Some(path) = &self.side_by_side {
+            render_side_by_side(&solved, self.reference)
+                .save(path)
+                .unwrap_or_else(|error| eprintln!("Failed to save side-by-side image {}: {}", path, error));
+        }
+
+        if let Some(path) = &self.difference {
+            let misplaced: Vec<(usize, usize)> = best
+                .iter()
+                .enumerate()
+                .flat_map(|(row, cells)| (0..cells.len()).map(move |col| (row, col)))
+                .filter(|&(row, col)| !best[row][col].is_empty() && !self.ground_truth.is_correct(best, row, col))
+                .collect();
+
+            render_difference(&solved, self.reference, &misplaced, self.pitch)
+                .save(path)
+                .unwrap_or_else(|error| eprintln!("Failed to save difference image {}: {}", path, error));
+        }
+    }
+}
+
+fn reference_at_size(reference: &DynamicImage, width: u32, height: u32) -> RgbaImage {
+    if reference.dimensions() == (width, height) {
+        return reference.to_rgba8();
+    }
+    imageops::resize(reference, width, height, imageops::FilterType::Triangle)
+}
+
+fn outline_cell(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32) {
+    let width = width.min(image.width().saturating_sub(x));
+    let height = height.min(image.height().saturating_sub(y));
+
+    for pixel_y in y..y + height {
+        for pixel_x in x..x + width {
+            let on_border = pixel_x < x + OUTLINE_WIDTH
+                || pixel_y < y + OUTLINE_WIDTH
+                || pixel_x + OUTLINE_WIDTH >= x + width
+                || pixel_y + OUTLINE_WIDTH >= y + height;
+            if on_border {
+                image.put_pixel(pixel_x, pixel_y, OUTLINE_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::fs;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::adjacency::AdjacencyData;
+use crate::dissimilarity::DissimilarityMatrix;
+use crate::observer::Observer;
+use crate::stats::GenerationStats;
+use crate::termination::StopReason;
+use crate::utils;
+
+const OVERLAY_OPACITY: f64 = 0.75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ConfidenceMetric {
+    Dissimilarity,
+    Rank,
+}
+
+impl ConfidenceMetric {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dissimilarity" => Some(ConfidenceMetric::Dissimilarity),
+            "rank" => Some(ConfidenceMetric::Rank),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeamConfidence {
+    pub first: String,
+    pub second: String,
+    pub first_cell: (usize, usize),
+    pub second_cell: (usize, usize),
+    pub relation: char,
+    pub dissimilarity: f64,
+    pub rank: Option<usize>,
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PieceConfidence {
+    pub piece: String,
+    pub row: usize,
+    pub col: usize,
+    pub seams: usize,
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceReport {
+    pub metric: ConfidenceMetric,
+    pub mean_confidence: f64,
+    pub seams: Vec<SeamConfidence>,
+    pub pieces: Vec<PieceConfidence>,
+}
+
+impl ConfidenceReport {
+    pub fn new(chromosome: &[Vec<String>], metric: ConfidenceMetric, adjacency: &AdjacencyData, dissimilarity_r: &DissimilarityMatrix, dissimilarity_d: &DissimilarityMatrix) -> Self {
+        let (height, width) = utils::matrix_size(chromosome);
+        let mut seams = Vec::new();
+
+        for row in 0..height {
+            for col in 0..width {
+                let first = &chromosome[row][col];
+                if first.is_empty() {
+                    continue;
+                }
+
+                for (relation, (second_row, second_col)) in utils::adjacent_cells((row, col), width, height) {
+                    let second = &chromosome[second_row][second_col];
+                    let dissimilarities = match relation {
+                        'R' => dissimilarity_r,
+                        'D' => dissimilarity_d,
+                        _ => continue,
+                    };
+                    if second.is_empty() {
+                        continue;
+                    }
+
+                    let dissimilarity = dissimilarities.get(&(first.clone(), second.clone())).copied().unwrap_or(f64::INFINITY);
+                    let rank = adjacency.get_most_compatible(first, relation).iter().position(|(_, key)| key == second);
+
+                    seams.push(SeamConfidence {
+                        first: first.clone(),
+                        second: second.clone(),
+                        first_cell: (row, col),
+                        second_cell: (second_row, second_col),
+                        relation,
+                        dissimilarity,
+                        rank,
+                        confidence: 0.0,
+                    });
+                }
+            }
+        }
+
+        score_seams(&mut seams, metric);
+        let pieces = score_pieces(chromosome, &seams);
+        let mean_confidence = seams.iter().map(|seam| seam.confidence).sum::<f64>() / seams.len().max(1) as f64;
+
+        Self { metric, mean_confidence, seams, pieces }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).map_err(|error| format!("Failed to serialize confidence report: {}", error))?;
+        fs::write(path, contents).map_err(|error| format!("Failed to write confidence report {}: {}", path, error))
+    }
+
+    pub fn render_heatmap(&self, solved: &RgbaImage, piece_width: u32, piece_height: u32) -> RgbaImage {
+        let mut heatmap = solved.clone();
+        let thickness = (piece_width.min(piece_height) / 12).max(2);
+
+        for seam in &self.seams {
+            let (row, col) = (seam.first_cell.0 as u32, seam.first_cell.1 as u32);
+            let (x, y, width, height) = match seam.relation {
+                'R' => ((col + 1) * piece_width - thickness, row * piece_height, 2 * thickness, piece_height),
+                _ => (col * piece_width, (row + 1) * piece_height - thickness, piece_width, 2 * thickness),
+            };
+
+            let color = confidence_color(seam.confidence);
+            for pixel_y in y..(y + height).min(heatmap.height()) {
+                for pixel_x in x..(x + width).min(heatmap.width()) {
+                    let pixel = heatmap.get_pixel_mut(pixel_x, pixel_y);
+                    *pixel = blend(*pixel, color);
+                }
+            }
+        }
+
+        heatmap
+    }
+}
+
+pub struct ConfidenceExporter<'a> {
+    pub pieces: &'a HashMap<String, DynamicImage>,
+    pub piece_size: (u32, u32),
+    pub adjacency: &'a AdjacencyData,
+    pub dissimilarity_r: &'a DissimilarityMatrix,
+    pub dissimilarity_d: &'a DissimilarityMatrix,
+    pub metric: ConfidenceMetric,
+    pub heatmap: Option<String>,
+    pub report: Option<String>,
+}
+
+impl Observer for ConfidenceExporter<'_> {
+    fn on_termination(&mut self, _reason: &StopReason, _stats: &GenerationStats, best: &[Vec<String>]) {
+        let report = ConfidenceReport::new(best, self.metric, self.adjacency, self.dissimilarity_r, self.dissimilarity_d);
+
+        if let Some(path) = &self.report {
+            report.save(path).unwrap_or_else(|error| eprintln!("{}", error));
+        }
+
+        if let Some(path) = &self.heatmap {
+            let (piece_width, piece_height) = self.piece_size;
+            let solved = utils::create_image_from_matrix(&utils::create_image_matrix(best, self.pieces, self.piece_size));
+            report
+                .render_heatmap(&solved, piece_width, piece_height)
+                .save(path)
+                .unwrap_or_else(|error| eprintln!("Failed to save heatmap {}: {}", path, error));
+        }
+    }
+}
+
+fn score_seams(seams: &mut [SeamConfidence], metric: ConfidenceMetric) {
+    let finite = seams.iter().map(|seam| seam.dissimilarity).filter(|dissimilarity| dissimilarity.is_finite());
+    let (lowest, highest) = finite.fold((f64::INFINITY, f64::NEG_INFINITY), |(lowest, highest), dissimilarity| (lowest.min(dissimilarity), highest.max(dissimilarity)));
+    let spread = highest - lowest;
+
+    for seam in seams.iter_mut() {
+        seam.confidence = match metric {
+            ConfidenceMetric::Dissimilarity if !seam.dissimilarity.is_finite() => 0.0,
+            ConfidenceMetric::Dissimilarity if spread > 0.0 => 1.0 - (seam.dissimilarity - lowest) / spread,
+            ConfidenceMetric::Dissimilarity => 1.0,
+            ConfidenceMetric::Rank => seam.rank.map_or(0.0, |rank| 1.0 / (rank + 1) as f64),
+        };
+    }
+}
+
+fn score_pieces(chromosome: &[Vec<String>], seams: &[SeamConfidence]) -> Vec<PieceConfidence> {
+    let mut totals: HashMap<(usize, usize), (usize, f64)> = HashMap::new();
+    for seam in seams {
+        for cell in [seam.first_cell, seam.second_cell] {
+            let total = totals.entry(cell).or_insert((0, 0.0));
+            total.0 += 1;
+            total.1 += seam.confidence;
+        }
+    }
+
+    let mut pieces = Vec::new();
+    for (row, cells) in chromosome.iter().enumerate() {
+        for (col, piece) in cells.iter().enumerate() {
+            if piece.is_empty() {
+                continue;
+            }
+
+            let (seams, total) = totals.get(&(row, col)).copied().unwrap_or((0, 0.0));
+            let confidence = if seams == 0 { 0.0 } else { total / seams as f64 };
+            pieces.push(PieceConfidence { piece: piece.clone(), row, col, seams, confidence });
+        }
+    }
+
+    pieces
+}
+
+fn confidence_color(confidence: f64) -> Rgba<u8> {
+    let confidence = confidence.clamp(0.0, 1.0);
+    let red = if confidence < 0.5 { 255.0 } else { 255.0 * (1.0 - confidence) * 2.0 };
+    let green = if confidence < 0.5 { 255.0 * confidence * 2.0 } else { 255.0 };
+    Rgba([red as u8, green as u8, 0, 255])
+}
+
+fn blend(base: Rgba<u8>, overlay: Rgba<u8>) -> Rgba<u8> {
+    let mix = |channel: usize| (base[channel] as f64 * (1.0 - OVERLAY_OPACITY) + overlay[channel] as f64 * OVERLAY_OPACITY) as u8;
+    Rgba([mix(0), mix(1), mix(2), 255])
+}
@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::adaptive::AdaptivePolicy;
+use crate::color_space::ColorSpace;
+use crate::confidence::ConfidenceMetric;
+use crate::crossover::CrossoverMethod;
+use crate::dissimilarity::DissimilarityMetric;
+use crate::diversity::RestartPolicy;
+use crate::elitism::EliteSize;
+use crate::init_population::SeedingRatios;
+use crate::island::MigrationTopology;
+use crate::selection::SelectionStrategy;
+use crate::stats::StatsFormat;
+use crate::termination::TerminationCriteria;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub pieces_directory: String,
+    pub original_image: String,
+    pub output_image: String,
+    pub population_size: usize,
+    pub dissimilarity: DissimilarityMetric,
+    pub color_space: ColorSpace,
+    pub termination: TerminationCriteria,
+    pub selection: SelectionStrategy,
+    pub crossover: CrossoverMethod,
+    pub elitism: EliteSize,
+    pub seeding: SeedingRatios,
+    pub replace_duplicates: bool,
+    pub restart: RestartPolicy,
+    pub mutation_rate: f64,
+    pub seed: Option<u64>,
+    pub islands: usize,
+    pub migration_interval: usize,
+    pub migrants: usize,
+    pub topology: MigrationTopology,
+    pub island_selection: Vec<SelectionStrategy>,
+    pub island_mutation: Vec<f64>,
+    pub adaptive: AdaptivePolicy,
+    pub checkpoint: Option<String>,
+    pub checkpoint_interval: usize,
+    pub stats: Option<String>,
+    pub stats_format: Option<StatsFormat>,
+    pub ground_truth: Option<String>,
+    pub export: Option<String>,
+    pub snapshot_interval: usize,
+    pub snapshot_directory: String,
+    pub animation: Option<String>,
+    pub frame_delay: u32,
+    pub heatmap: Option<String>,
+    pub confidence_report: Option<String>,
+    pub confidence_metric: ConfidenceMetric,
+    pub side_by_side: Option<String>,
+    pub difference: Option<String>,
+    pub report: Option<String>,
+    #[serde(skip)]
+    pub resume: Option<String>,
+    #[serde(skip)]
+    pub render: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            pieces_directory: "../slika 5".to_string(),
+            original_image: "../picture5.jpg".to_string(),
+            output_image: "solved.png".to_string(),
+            population_size: 500,
+            dissimilarity: DissimilarityMetric::Euclidean,
+            color_space: ColorSpace::Rgb,
+            termination: TerminationCriteria {
+                max_generations: Some(30),
+                ..TerminationCriteria::default()
+            },
+            selection: SelectionStrategy::Roulette(1.0),
+            crossover: CrossoverMethod::Kernel,
+            elitism: EliteSize::Count(4),
+            seeding: SeedingRatios::default(),
+            replace_duplicates: true,
+            restart: RestartPolicy::default(),
+            mutation_rate: 0.0,
+            seed: None,
+            islands: 1,
+            migration_interval: 10,
+            migrants: 2,
+            topology: MigrationTopology::Ring,
+            island_selection: Vec::new(),
+            island_mutation: Vec::new(),
+            adaptive: AdaptivePolicy::default(),
+            checkpoint: None,
+            checkpoint_interval: 10,
+            stats: None,
+            stats_format: None,
+            ground_truth: None,
+            export: None,
+            snapshot_interval: 0,
+            snapshot_directory: "snapshots".to_string(),
+            animation: None,
+            frame_delay: 200,
+            heatmap: None,
+            confidence_report: None,
+            confidence_metric: ConfidenceMetric::Dissimilarity,
+            side_by_side: None,
+            difference: None,
+            report: None,
+            resume: None,
+            render: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        Config::default().with_args(args)
+    }
+
+    pub fn with_args<I: Iterator<Item = String>>(self, mut args: I) -> Result<Self, String> {
+        let mut config = self;
+
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or(format!("Missing value for {}", flag))?;
+
+            match flag.as_str() {
+                "--pieces" => config.pieces_directory = value,
+                "--original" => config.original_image = value,
+                "--output" => config.output_image = value,
+                "--population" => config.population_size = parse_value(&flag, &value)?,
+                "--crossover" => {
+                    config.crossover = CrossoverMethod::from_name(&value)
+                        .ok_or(format!("Unknown crossover operator: {}", value))?
+                }
+                "--color-space" => {
+                    config.color_space = ColorSpace::from_name(&value)
+                        .ok_or(format!("Unknown color space: {}", value))?
+                }
+                "--dissimilarity" => {
+                    config.dissimilarity = DissimilarityMetric::from_name(&value)
+                        .ok_or(format!("Unknown dissimilarity metric: {}", value))?
+                }
+                "--generations" => {
+                    let generations: usize = parse_value(&flag, &value)?;
+                    config.termination.max_generations = Some(generations).filter(|&generations| generations > 0);
+                }
+                "--time-limit" => {
+                    let seconds: f64 = parse_value(&flag, &value)?;
+                    config.termination.time_budget = Some(Duration::try_from_secs_f64(seconds).map_err(|_| format!("Invalid value for {}: {}", flag, value))?);
+                }
+                "--stop-after-stagnant" => config.termination.stagnation_limit = Some(parse_value(&flag, &value)?),
+                "--target-fitness" => config.termination.target_fitness = Some(parse_value(&flag, &value)?),
+                "--stop-on-identical-elites" => config.termination.stop_on_identical_elites = parse_value(&flag, &value)?,
+                "--selection" => {
+                    config.selection = SelectionStrategy::from_name(&value)
+                        .ok_or(format!("Unknown selection strategy: {}", value))?
+                }
+                "--elitism" => {
+                    config.elitism = EliteSize::from_name(&value)
+                        .ok_or(format!("Invalid elitism size: {}", value))?
+                }
+                "--seed-greedy" => config.seeding.greedy = parse_value(&flag, &value)?,
+                "--seed-buddies" => config.seeding.best_buddy = parse_value(&flag, &value)?,
+                "--duplicates" => {
+                    config.replace_duplicates = match value.as_str() {
+                        "replace" => true,
+                        "keep" => false,
+                        _ => return Err(format!("Invalid value for {}: {}", flag, value)),
+                    }
+                }
+                "--restart-after" => config.restart.stagnation_limit = parse_value(&flag, &value)?,
+                "--min-diversity" => config.restart.min_diversity = parse_value(&flag, &value)?,
+                "--restart-fraction" => config.restart.fraction = parse_value(&flag, &value)?,
+                "--mutation-rate" => config.mutation_rate = parse_value(&flag, &value)?,
+                "--seed" => config.seed = Some(parse_value(&flag, &value)?),
+                "--islands" => config.islands = parse_value(&flag, &value)?,
+                "--migration-interval" => config.migration_interval = parse_value(&flag, &value)?,
+                "--migrants" => config.migrants = parse_value(&flag, &value)?,
+                "--topology" => {
+                    config.topology = MigrationTopology::from_name(&value)
+                        .ok_or(format!("Unknown migration topology: {}", value))?
+                }
+                "--island-selection" => {
+                    config.island_selection = value
+                        .split(',')
+                        .map(|name| SelectionStrategy::from_name(name).ok_or(format!("Unknown selection strategy: {}", name)))
+                        .collect::<Result<_, _>>()?
+                }
+                "--island-mutation" => {
+                    config.island_mutation = value
+                        .split(',')
+                        .map(|rate| parse_value(&flag, rate))
+                        .collect::<Result<_, _>>()?
+                }
+                "--adaptive" => config.adaptive.stagnation_limit = parse_value(&flag, &value)?,
+                "--max-mutation-rate" => config.adaptive.max_mutation_rate = parse_value(&flag, &value)?,
+                "--checkpoint" => config.checkpoint = Some(value),
+                "--checkpoint-interval" => config.checkpoint_interval = parse_value(&flag, &value)?,
+                "--stats" => config.stats = Some(value),
+                "--stats-format" => {
+                    config.stats_format = Some(StatsFormat::from_name(&value)
+                        .ok_or(format!("Unknown statistics format: {}", value))?)
+                }
+                "--ground-truth" => config.ground_truth = Some(value),
+                "--export" => config.export = Some(value),
+                "--snapshot-every" => config.snapshot_interval = parse_value(&flag, &value)?,
+                "--snapshot-dir" => config.snapshot_directory = value,
+                "--animation" => config.animation = Some(value),
+                "--frame-delay" => config.frame_delay = parse_value(&flag, &value)?,
+                "--heatmap" => config.heatmap = Some(value),
+                "--confidence-report" => config.confidence_report = Some(value),
+                "--side-by-side" => config.side_by_side = Some(value),
+                "--difference" => config.difference = Some(value),
+                "--report" => config.report = Some(value),
+                "--confidence-metric" => {
+                    config.confidence_metric = ConfidenceMetric::from_name(&value)
+                        .ok_or(format!("Unknown confidence metric: {}", value))?
+                }
+                "--resume" => config.resume = Some(value),
+                "--render" => config.render = Some(value),
+                _ => return Err(format!("Unknown option: {}", flag)),
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the settings `with_args` cannot enforce flag by flag, so a
+    /// config built in code is held to the same rules as the command line.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.population_size == 0 {
+            return Err("Population size must be positive".to_string());
+        }
+
+        if self.islands == 0 {
+            return Err("At least one island is required".to_string());
+        }
+
+        if !self.selection.is_valid() || !self.island_selection.iter().all(SelectionStrategy::is_valid) {
+            return Err("Tournament size must be positive, rank pressure between 1 and 2 and fitness exponents between 0 and 8".to_string());
+        }
+
+        if !self.elitism.is_valid() {
+            return Err("Elitism percentage must be between 0 and 100".to_string());
+        }
+
+        if !self.seeding.is_valid() {
+            return Err("Seeding ratios must be between 0 and 1 and add up to at most 1".to_string());
+        }
+
+        if !(0.0..=1.0).contains(&self.restart.fraction) {
+            return Err("Restart fraction must be between 0 and 1".to_string());
+        }
+
+        let mut mutation_rates = [self.mutation_rate, self.adaptive.max_mutation_rate].into_iter().chain(self.island_mutation.iter().cloned());
+        if mutation_rates.any(|rate| !(0.0..=1.0).contains(&rate)) {
+            return Err("Mutation rates must be between 0 and 1".to_string());
+        }
+
+        if !self.termination.is_bounded() {
+            return Err("At least one termination criterion is required".to_string());
+        }
+
+        if self.replace_duplicates && !self.termination.has_hard_limit() {
+            return Err("--stop-on-identical-elites alone requires --duplicates keep, replaced duplicates keep elites distinct".to_string());
+        }
+
+        if self.animation.is_some() && self.snapshot_interval == 0 {
+            return Err("--animation requires --snapshot-every".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+pub fn resume_path(args: &[String]) -> Option<&str> {
+    args.iter()
+        .position(|arg| arg == "--resume")
+        .and_then(|position| args.get(position + 1))
+        .map(|path| path.as_str())
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
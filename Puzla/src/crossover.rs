@@ -1,13 +1,132 @@
-use std::collections::{HashMap, BinaryHeap};
+use std::collections::{HashMap, HashSet, BinaryHeap};
 use std::cmp::{Ordering, Reverse};
 
-use rand::Rng;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
 
 use crate::adjacency::AdjacencyData;
 use crate::utils::{self, build_matrix};
 
 use ordered_float::OrderedFloat;
 
+pub trait CrossoverOperator: Sync {
+    /// Recombines two parents into a child, also returning how many
+    /// placements had to fall back to plain compatibility ranking.
+    fn crossover(&self, parent1: &[Vec<String>], parent2: &[Vec<String>], adjacency: &AdjacencyData, rng: &mut dyn RngCore) -> (Vec<Vec<String>>, usize);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CrossoverMethod {
+    Kernel,
+    Block,
+}
+
+impl CrossoverMethod {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "kernel" => Some(CrossoverMethod::Kernel),
+            "block" => Some(CrossoverMethod::Block),
+            _ => None,
+        }
+    }
+
+    pub fn operator(&self) -> Box<dyn CrossoverOperator> {
+        match self {
+            CrossoverMethod::Kernel => Box::new(KernelCrossover),
+            CrossoverMethod::Block => Box::new(BlockCrossover),
+        }
+    }
+}
+
+/// Grows the child from a random root piece, preferring placements both
+/// parents agree on, then best buddies, then the most compatible piece.
+/// Candidates come from every piece, so pieces in a parent's rejection pool
+/// can be swapped back in while others are left out. Parents the kernel
+/// cannot be grown from within a few attempts are recombined by
+/// `BlockCrossover` instead.
+pub struct KernelCrossover;
+
+const MAX_KERNEL_ATTEMPTS: usize = 10;
+
+impl CrossoverOperator for KernelCrossover {
+    fn crossover(&self, parent1: &[Vec<String>], parent2: &[Vec<String>], adjacency: &AdjacencyData, mut rng: &mut dyn RngCore) -> (Vec<Vec<String>>, usize) {
+        let mut fallbacks = 0;
+        let attempts = if utils::placed_piece_count(parent1) == 0 { 0 } else { MAX_KERNEL_ATTEMPTS };
+
+        for _ in 0..attempts {
+            let mut crossover = Crossover::new(parent1, parent2, adjacency);
+            let child = crossover.generate_child(&mut rng);
+            fallbacks += crossover.fallbacks();
+
+            if let Some(child) = child {
+                return (child, fallbacks);
+            }
+        }
+
+        let (child, _) = BlockCrossover.crossover(parent1, parent2, adjacency, rng);
+        (child, fallbacks)
+    }
+}
+
+/// Keeps a random rectangle of the first parent in place and fills the
+/// remaining cells with the unused pieces in the order the second parent
+/// has them, pulling in pieces the first parent had rejected.
+pub struct BlockCrossover;
+
+impl CrossoverOperator for BlockCrossover {
+    fn crossover(&self, parent1: &[Vec<String>], parent2: &[Vec<String>], _adjacency: &AdjacencyData, rng: &mut dyn RngCore) -> (Vec<Vec<String>>, usize) {
+        let (rows, columns) = utils::matrix_size(parent1);
+        if rows == 0 || columns == 0 {
+            return (parent1.to_vec(), 0);
+        }
+
+        let (top, bottom) = random_span(rows, rng);
+        let (left, right) = random_span(columns, rng);
+        let in_block = |row: usize, col: usize| (top..=bottom).contains(&row) && (left..=right).contains(&col);
+
+        let mut used: HashSet<&String> = HashSet::new();
+        let mut empty_cells = parent1.iter().flatten().filter(|piece| piece.is_empty()).count();
+        for (row, cells) in parent1.iter().enumerate() {
+            for (col, piece) in cells.iter().enumerate() {
+                if in_block(row, col) {
+                    if piece.is_empty() {
+                        empty_cells -= 1;
+                    } else {
+                        used.insert(piece);
+                    }
+                }
+            }
+        }
+
+        let mut remaining = parent2.iter().flatten().filter(|piece| {
+            if piece.is_empty() {
+                let keep = empty_cells > 0;
+                empty_cells = empty_cells.saturating_sub(1);
+                keep
+            } else {
+                !used.contains(piece)
+            }
+        });
+
+        let child = parent1
+            .iter()
+            .enumerate()
+            .map(|(row, cells)| {
+                (0..cells.len())
+                    .map(|col| if in_block(row, col) { cells[col].clone() } else { remaining.next().cloned().unwrap_or_default() })
+                    .collect()
+            })
+            .collect();
+
+        (child, 0)
+    }
+}
+
+fn random_span(length: usize, rng: &mut dyn RngCore) -> (usize, usize) {
+    let first = rng.gen_range(0..length);
+    let second = rng.gen_range(0..length);
+    (first.min(second), first.max(second))
+}
 
 #[derive(Debug, PartialEq, Eq)]
 struct Candidate(String, (i32, i32), (String, String), OrderedFloat<f64>);
@@ -27,14 +146,17 @@ impl PartialOrd for Candidate {
 const MUTUALLY_AGREED_PRIORITY: OrderedFloat<f64> = OrderedFloat(-2.0);
 const BUDDY_PRIORITY: OrderedFloat<f64> = OrderedFloat(-1.0);
 
+/// Kernel growing state for a single child of `KernelCrossover`.
 pub struct Crossover<'a>  {
     rows: usize,
     columns: usize,
+    target_size: usize,
+    fallbacks: usize,
     occupied_positions: Vec<(i32, i32)>,
     kernel:  HashMap<String, (i32, i32)>,
     candidates: BinaryHeap<Reverse<Candidate>>,
-    parent1: &'a Vec<Vec<String>>,
-    parent2: &'a Vec<Vec<String>>,
+    parent1: &'a [Vec<String>],
+    parent2: &'a [Vec<String>],
     adjacency: &'a AdjacencyData,
     max_row: i32,
     max_col: i32, 
@@ -44,9 +166,11 @@ pub struct Crossover<'a>  {
 
 impl<'a> Crossover<'a> {
 
-    pub fn new(parent1: &'a Vec<Vec<String>>, parent2: &'a Vec<Vec<String>>, adjacency: &'a AdjacencyData) -> Self {
+    /// The child gets the grid size and placed piece count of `parent1`.
+    pub fn new(parent1: &'a [Vec<String>], parent2: &'a [Vec<String>], adjacency: &'a AdjacencyData) -> Self {
         let rows = parent1.len();
-        let columns = parent1.get(0).map_or(0, |row| row.len());
+        let columns = parent1.first().map_or(0, |row| row.len());
+        let target_size = utils::placed_piece_count(parent1);
     
         let occupied_positions: Vec<(i32, i32)> = Vec::new();
     
@@ -54,6 +178,8 @@ impl<'a> Crossover<'a> {
 
         let kernel: HashMap<String, (i32, i32)> = HashMap::new();
 
+        let fallbacks = 0;
+
         let max_row = 0;
         let min_row = 0;
         let max_col = 0;
@@ -61,18 +187,20 @@ impl<'a> Crossover<'a> {
  
 
         Self {
-            parent1, parent2, rows, columns, occupied_positions, 
+            parent1, parent2, rows, columns, target_size, fallbacks, occupied_positions, 
             kernel, adjacency, min_col, max_col, min_row, max_row,
             candidates
         }
     }
 
-    pub fn generate_child(&mut self) -> Option<Vec<Vec<String>>> {
-        let root_piece = self.generate_root_piece();
+    /// Grows the kernel until it holds as many pieces as `parent1` places,
+    /// or returns `None` if it runs out of candidates first.
+    pub fn generate_child<R: Rng>(&mut self, rng: &mut R) -> Option<Vec<Vec<String>>> {
+        let root_piece = self.generate_root_piece(rng);
 
         self.add_to_kernel(root_piece, (0, 0));
 
-        while self.candidates.len() != 0 {
+        while !self.candidates.is_empty() {
             let Reverse(candidate) = self.candidates.pop().unwrap();
 
             if self.occupied_positions.contains(&candidate.1) {
@@ -84,18 +212,30 @@ impl<'a> Crossover<'a> {
                 continue;
             }
 
+            if candidate.3 > BUDDY_PRIORITY {
+                self.fallbacks += 1;
+            }
+
             self.add_to_kernel(candidate.0.clone(), candidate.1);
         }
         
         
-        if self.kernel.len() != self.rows * self.columns {
+        if self.kernel.len() != self.target_size {
             return None;
         }
          
-        Some(build_matrix(&self.kernel, self.min_col, self.max_col, self.min_row, self.max_row))
+        let max_col = self.min_col + self.columns as i32 - 1;
+        let max_row = self.min_row + self.rows as i32 - 1;
+        Some(build_matrix(&self.kernel, self.min_col, max_col, self.min_row, max_row))
     }
 
 
+    /// Pieces placed by compatibility ranking alone, without parental
+    /// agreement or a best buddy to back them.
+    pub fn fallbacks(&self) -> usize {
+        self.fallbacks
+    }
+
     fn add_to_kernel(&mut self, piece: String, position: (i32, i32)) {
         self.kernel.insert(piece.clone(), position);
         self.occupied_positions.push(position);
@@ -110,7 +250,7 @@ impl<'a> Crossover<'a> {
     }
 
     fn add_candidate(&mut self, piece: &String, relation: String, position: (i32, i32)) {
-        let mutually_agreed = self.get_mutually_agreed_piece(&piece, &relation);
+        let mutually_agreed = self.get_mutually_agreed_piece(piece, &relation);
         if let Some(agreed) = mutually_agreed {
             if self.piece_is_available(&agreed) {
                 let candidate = Candidate(agreed, position, (piece.clone(), relation.clone()), MUTUALLY_AGREED_PRIORITY);
@@ -128,7 +268,7 @@ impl<'a> Crossover<'a> {
             }
         }
 
-        let most_compatible = self.adjacency.get_most_compatible(&piece, relation.chars().next().unwrap());
+        let most_compatible = self.adjacency.get_most_compatible(piece, relation.chars().next().unwrap());
         for (dissimilarity, ref compatible_piece) in most_compatible.iter() {
             if self.piece_is_available(compatible_piece) {
                 let candidate = Candidate(compatible_piece.clone(), position, (piece.clone(), relation.clone()), OrderedFloat(*dissimilarity));
@@ -138,20 +278,15 @@ impl<'a> Crossover<'a> {
         }
     }
 
-    fn get_best_buddy(&self, piece: &String, relation: &String) -> Option<String> {
-        let best_buddy = self.adjacency.get_best_buddy_in_relation(&piece, relation.chars().next().unwrap());
-        
-        if best_buddy == None {
-            return None;
-        }
+    fn get_best_buddy(&self, piece: &String, relation: &str) -> Option<String> {
+        let best_buddy = self.adjacency.get_best_buddy_in_relation(piece, relation.chars().next().unwrap())?;
 
-        let best_buddy = best_buddy.unwrap();
-        let parent1_contains_bb = self.contains_best_buddies(self.parent1, &piece, &best_buddy, &relation);
+        let parent1_contains_bb = self.contains_best_buddies(self.parent1, piece, &best_buddy, relation);
         if parent1_contains_bb {
             return Some(best_buddy);
         }
 
-        let parent2_contains_bb = self.contains_best_buddies(self.parent2, &piece, &best_buddy, &relation);
+        let parent2_contains_bb = self.contains_best_buddies(self.parent2, piece, &best_buddy, relation);
         if parent2_contains_bb {
             return Some(best_buddy);
         }
@@ -159,8 +294,11 @@ impl<'a> Crossover<'a> {
         None
     }
 
-    fn contains_best_buddies(&self, image: &Vec<Vec<String>>, buddy1: &String, buddy2: &String, relation: &str) -> bool {
-        let (row, col) = utils::find_position_in_matrix(image, buddy1).unwrap();
+    fn contains_best_buddies(&self, image: &[Vec<String>], buddy1: &str, buddy2: &String, relation: &str) -> bool {
+        let (row, col) = match utils::find_position_in_matrix(image, buddy1) {
+            Some(position) => position,
+            None => return false,
+        };
         let image_dims = utils::matrix_size(image);
 
         let check_position = match relation {
@@ -196,17 +334,17 @@ impl<'a> Crossover<'a> {
         };
         match check_position {
             Some((check_row, check_col)) => {
-                image.get(check_row).map_or(false, |row| row.get(check_col) == Some(buddy2))
+                image.get(check_row).is_some_and(|row| row.get(check_col) == Some(buddy2))
             }
             None => false,
         }
 
     }
  
-    fn get_mutually_agreed_piece(&mut self, piece: &String, relation: &String) -> Option<String> {
-        let image_dims = utils::matrix_size(&self.parent1);
-        let (row, col) = utils::find_position_in_matrix(self.parent1, &piece).unwrap();
-        let check_position = match relation.as_str() {
+    fn get_mutually_agreed_piece(&mut self, piece: &str, relation: &str) -> Option<String> {
+        let image_dims = utils::matrix_size(self.parent1);
+        let (row, col) = utils::find_position_in_matrix(self.parent1, piece)?;
+        let check_position = match relation {
             "U" => {
                 if row > 0 {
                     Some((row - 1, col))
@@ -238,17 +376,13 @@ impl<'a> Crossover<'a> {
             _ => None,
         };
 
-        if check_position == None {
-            return None;
-        }
-
-        let check_position = check_position.unwrap();
+        let check_position = check_position?;
 
         let piece_in_parent1 =  self.parent1.get(check_position.0)?.get(check_position.1).unwrap();
         
-        let (row, col) = utils::find_position_in_matrix(self.parent2, &piece).unwrap();
+        let (row, col) = utils::find_position_in_matrix(self.parent2, piece)?;
 
-        let check_position = match relation.as_str() {
+        let check_position = match relation {
             "U" => {
                 if row > 0 {
                     Some((row - 1, col))
@@ -280,14 +414,11 @@ impl<'a> Crossover<'a> {
             _ => None,
         };
 
-        if check_position == None {
-            return None;
-        }
-        let check_position = check_position.unwrap();
+        let check_position = check_position?;
 
         let piece_in_parent2 =  self.parent2.get(check_position.0)?.get(check_position.1).unwrap();
 
-        if piece_in_parent1 == piece_in_parent2 {
+        if piece_in_parent1 == piece_in_parent2 && !piece_in_parent1.is_empty() {
             return Some(piece_in_parent1.clone());
         }
 
@@ -299,15 +430,12 @@ impl<'a> Crossover<'a> {
         let mut boundaries = Vec::new();
 
         if !self.kernel_is_full() {
-            let positions: HashMap<&str, (i32, i32)> = [
+            let positions: [(&str, (i32, i32)); 4] = [
                 ("U", (row - 1, column)),
                 ("R", (row, column + 1)),
                 ("D", (row + 1, column)),
                 ("L", (row, column - 1)),
-            ]
-            .iter()
-            .cloned()
-            .collect();
+            ];
                         
             for (orientation, position) in positions.iter() {
 
@@ -344,21 +472,106 @@ impl<'a> Crossover<'a> {
     }
 
     fn kernel_is_full(&self) -> bool {
-        self.kernel.len() == self.rows * self.columns
+        self.kernel.len() == self.target_size
     }
 
     fn piece_is_available(&self, piece: &String) -> bool {
         !self.kernel.contains_key(piece)
     }
 
-    fn generate_root_piece(&self) -> String {
-        let mut rng = rand::thread_rng();
-    
-        let random_row = rng.gen_range(0..self.rows);
-        let random_col = rng.gen_range(0..self.columns);
+    fn generate_root_piece<R: Rng>(&self, rng: &mut R) -> String {
+        let placed_pieces: Vec<&String> = self.parent1.iter().flatten().filter(|piece| !piece.is_empty()).collect();
     
-        let root_piece = self.parent1.get(random_row).and_then(|row| row.get(random_col));
+        let root_piece = placed_pieces.get(rng.gen_range(0..placed_pieces.len().max(1)));
     
-        root_piece.cloned().unwrap_or_default()
+        root_piece.map(|piece| (*piece).clone()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::dissimilarity::DissimilarityMatrix;
+
+    fn adjacency(piece_keys: &[String]) -> AdjacencyData {
+        let costs: DissimilarityMatrix = piece_keys
+            .iter()
+            .flat_map(|first| piece_keys.iter().filter(move |second| *second != first).map(move |second| ((first.clone(), second.clone()), 1.0)))
+            .collect();
+        AdjacencyData::new(piece_keys, &costs, &costs)
+    }
+
+    fn shuffled_grid(cells: &[String], columns: usize, rng: &mut ChaCha8Rng) -> Vec<Vec<String>> {
+        let mut cells = cells.to_vec();
+        cells.shuffle(rng);
+        cells.chunks(columns).map(|row| row.to_vec()).collect()
+    }
+
+    fn sorted_cells(chromosome: &[Vec<String>]) -> Vec<String> {
+        let mut cells: Vec<String> = chromosome.iter().flatten().cloned().collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn block_crossover_rearranges_the_first_parent() {
+        // Ten pieces on a 4 x 3 grid, so both parents have two empty cells.
+        let piece_keys: Vec<String> = (0..10).map(|i| format!("piece{}", i)).collect();
+        let mut cells = piece_keys.clone();
+        cells.resize(12, String::new());
+        let adjacency = adjacency(&piece_keys);
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+
+        for _ in 0..200 {
+            let parent1 = shuffled_grid(&cells, 4, &mut rng);
+            let parent2 = shuffled_grid(&cells, 4, &mut rng);
+            let (child, fallbacks) = BlockCrossover.crossover(&parent1, &parent2, &adjacency, &mut rng);
+
+            assert_eq!(utils::matrix_size(&child), (3, 4));
+            assert_eq!(sorted_cells(&child), sorted_cells(&parent1));
+            assert_eq!(fallbacks, 0);
+        }
+    }
+
+    #[test]
+    fn kernel_crossover_gives_up_on_parents_it_cannot_grow_from() {
+        let piece_keys: Vec<String> = (0..4).map(|i| format!("piece{}", i)).collect();
+        let adjacency = adjacency(&piece_keys);
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+
+        let empty = vec![vec![String::new(); 2]; 2];
+        let (child, _) = KernelCrossover.crossover(&empty, &empty, &adjacency, &mut rng);
+        assert_eq!(child, empty);
+
+        let (child, _) = KernelCrossover.crossover(&[], &[], &adjacency, &mut rng);
+        assert!(child.is_empty());
+    }
+
+    #[test]
+    fn block_crossover_never_duplicates_pooled_pieces() {
+        // Eleven pieces for nine cells, and the parents pool different pieces.
+        let piece_keys: Vec<String> = (0..11).map(|i| format!("piece{}", i)).collect();
+        let adjacency = adjacency(&piece_keys);
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+
+        for _ in 0..200 {
+            let mut keys = piece_keys.clone();
+            keys.shuffle(&mut rng);
+            let parent1 = shuffled_grid(&keys[..9], 3, &mut rng);
+            keys.shuffle(&mut rng);
+            let parent2 = shuffled_grid(&keys[..9], 3, &mut rng);
+            let (child, _) = BlockCrossover.crossover(&parent1, &parent2, &adjacency, &mut rng);
+
+            let placed = sorted_cells(&child);
+            let mut distinct = placed.clone();
+            distinct.dedup();
+            assert_eq!(placed.len(), 9);
+            assert_eq!(distinct, placed);
+            assert!(placed.iter().all(|piece| piece_keys.contains(piece)));
+        }
     }
 }
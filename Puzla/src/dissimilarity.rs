@@ -2,6 +2,265 @@ use std::collections::HashMap;
 use rayon::prelude::*;
 
 use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+
+use crate::color_space;
+use crate::problem::GridProblem;
+
+pub type DissimilarityMatrix = HashMap<(String, String), f64>;
+
+/// How dissimilar two pieces are along the edge where `piece2` touches
+/// `piece1` in direction `relation` ('L', 'R', 'U' or 'D'). Lower is better.
+pub trait Dissimilarity: Sync {
+    fn dissimilarity(&self, piece1: &DynamicImage, piece2: &DynamicImage, relation: char) -> f64;
+}
+
+/// Square root of the summed squared color differences of the touching edge pixels.
+pub struct Euclidean;
+
+impl Dissimilarity for Euclidean {
+    fn dissimilarity(&self, piece1: &DynamicImage, piece2: &DynamicImage, relation: char) -> f64 {
+        calculate_dissimilarity(piece1, piece2, relation)
+    }
+}
+
+/// Gallagher's Mahalanobis gradient compatibility: how well the gradient
+/// across the seam continues the gradient distribution next to each piece's
+/// edge, measured from both sides.
+pub struct Mgc;
+
+impl Dissimilarity for Mgc {
+    fn dissimilarity(&self, piece1: &DynamicImage, piece2: &DynamicImage, relation: char) -> f64 {
+        let boundary = match Boundary::new(piece1, piece2, relation) {
+            Some(boundary) => boundary,
+            None => return -1.0,
+        };
+
+        let inside_first: Vec<[f64; 3]> = boundary.first_edge.iter().zip(&boundary.first_inner).map(|(edge, inner)| difference(edge, inner)).collect();
+        let inside_second: Vec<[f64; 3]> = boundary.second_edge.iter().zip(&boundary.second_inner).map(|(edge, inner)| difference(edge, inner)).collect();
+        let across_first: Vec<[f64; 3]> = boundary.second_edge.iter().zip(&boundary.first_edge).map(|(second, first)| difference(second, first)).collect();
+        let across_second: Vec<[f64; 3]> = boundary.first_edge.iter().zip(&boundary.second_edge).map(|(first, second)| difference(first, second)).collect();
+
+        mahalanobis_sum(&inside_first, &across_first) + mahalanobis_sum(&inside_second, &across_second)
+    }
+}
+
+/// Pomeranz's (Lp)^q dissimilarity of the touching edge pixels,
+/// `(sum |a - b|^p)^(q/p)`.
+pub struct LpQ {
+    pub p: f64,
+    pub q: f64,
+}
+
+impl Dissimilarity for LpQ {
+    fn dissimilarity(&self, piece1: &DynamicImage, piece2: &DynamicImage, relation: char) -> f64 {
+        let boundary = match Boundary::new(piece1, piece2, relation) {
+            Some(boundary) => boundary,
+            None => return -1.0,
+        };
+
+        let sum: f64 = boundary
+            .first_edge
+            .iter()
+            .zip(&boundary.second_edge)
+            .flat_map(|(first, second)| difference(first, second))
+            .map(|delta| delta.abs().powf(self.p))
+            .sum();
+
+        sum.powf(self.q / self.p)
+    }
+}
+
+/// Pomeranz's prediction-based compatibility: each piece's last two lines
+/// are extrapolated linearly across the seam and compared with the line the
+/// neighbour actually has there, in both directions.
+pub struct Prediction;
+
+impl Dissimilarity for Prediction {
+    fn dissimilarity(&self, piece1: &DynamicImage, piece2: &DynamicImage, relation: char) -> f64 {
+        let boundary = match Boundary::new(piece1, piece2, relation) {
+            Some(boundary) => boundary,
+            None => return -1.0,
+        };
+
+        let prediction_error = |edge: &[[f64; 3]], inner: &[[f64; 3]], neighbour: &[[f64; 3]]| -> f64 {
+            edge.iter()
+                .zip(inner)
+                .zip(neighbour)
+                .flat_map(|((edge, inner), actual)| (0..3).map(move |channel| 2.0 * edge[channel] - inner[channel] - actual[channel]))
+                .map(|error| error * error)
+                .sum()
+        };
+
+        let forward = prediction_error(&boundary.first_edge, &boundary.first_inner, &boundary.second_edge);
+        let backward = prediction_error(&boundary.second_edge, &boundary.second_inner, &boundary.first_edge);
+
+        (forward + backward).sqrt()
+    }
+}
+
+const DEFAULT_P: f64 = 0.3;
+const DEFAULT_Q: f64 = 1.0 / 16.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DissimilarityMetric {
+    Euclidean,
+    Mgc,
+    LpQ(f64, f64),
+    Prediction,
+}
+
+impl DissimilarityMetric {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mut parts = name.splitn(2, ':');
+        let metric = parts.next()?;
+        let parameter = parts.next();
+
+        match metric {
+            "euclidean" if parameter.is_none() => Some(DissimilarityMetric::Euclidean),
+            "mgc" if parameter.is_none() => Some(DissimilarityMetric::Mgc),
+            "prediction" if parameter.is_none() => Some(DissimilarityMetric::Prediction),
+            "lpq" => {
+                let (p, q) = match parameter {
+                    Some(exponents) => {
+                        let (p, q) = exponents.split_once(',')?;
+                        (p.parse().ok()?, q.parse().ok()?)
+                    }
+                    None => (DEFAULT_P, DEFAULT_Q),
+                };
+                if !(p > 0.0 && q > 0.0) {
+                    return None;
+                }
+                Some(DissimilarityMetric::LpQ(p, q))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn metric(&self) -> Box<dyn Dissimilarity> {
+        match *self {
+            DissimilarityMetric::Euclidean => Box::new(Euclidean),
+            DissimilarityMetric::Mgc => Box::new(Mgc),
+            DissimilarityMetric::LpQ(p, q) => Box::new(LpQ { p, q }),
+            DissimilarityMetric::Prediction => Box::new(Prediction),
+        }
+    }
+}
+
+// Gradients added to every edge's sample so the covariance stays invertible
+// on flat edges, as in Gallagher's paper.
+const DUMMY_GRADIENTS: [[f64; 3]; 9] = [
+    [0.0, 0.0, 0.0],
+    [1.0, 1.0, 1.0],
+    [-1.0, -1.0, -1.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 1.0, 0.0],
+    [1.0, 0.0, 0.0],
+    [-1.0, 0.0, 0.0],
+    [0.0, -1.0, 0.0],
+    [0.0, 0.0, -1.0],
+];
+
+/// Pixel lines on both sides of the seam between two pieces, with `first`
+/// always the left or upper piece. Edge lines touch the seam, inner lines
+/// are one pixel further in.
+struct Boundary {
+    first_inner: Vec<[f64; 3]>,
+    first_edge: Vec<[f64; 3]>,
+    second_edge: Vec<[f64; 3]>,
+    second_inner: Vec<[f64; 3]>,
+}
+
+impl Boundary {
+    fn new(piece1: &DynamicImage, piece2: &DynamicImage, relation: char) -> Option<Self> {
+        let (first, second, horizontal) = match relation {
+            'R' => (piece1, piece2, true),
+            'L' => (piece2, piece1, true),
+            'D' => (piece1, piece2, false),
+            'U' => (piece2, piece1, false),
+            _ => return None,
+        };
+
+        let (first_width, first_height) = first.dimensions();
+        let (second_width, second_height) = second.dimensions();
+
+        let line = |piece: &DynamicImage, index: u32| -> Vec<[f64; 3]> {
+            if horizontal {
+                (0..first_height.min(second_height)).map(|i| color_space::channels(piece, index, i)).collect()
+            } else {
+                (0..first_width.min(second_width)).map(|i| color_space::channels(piece, i, index)).collect()
+            }
+        };
+
+        let (first_length, second_length) = if horizontal { (first_width, second_width) } else { (first_height, second_height) };
+        if first_length < 2 || second_length < 2 {
+            return None;
+        }
+
+        Some(Self {
+            first_inner: line(first, first_length - 2),
+            first_edge: line(first, first_length - 1),
+            second_edge: line(second, 0),
+            second_inner: line(second, 1),
+        })
+    }
+}
+
+fn difference(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn mahalanobis_sum(inside: &[[f64; 3]], across: &[[f64; 3]]) -> f64 {
+    let samples: Vec<[f64; 3]> = inside.iter().chain(DUMMY_GRADIENTS.iter()).cloned().collect();
+    let count = samples.len() as f64;
+
+    let mut mean = [0.0; 3];
+    for sample in &samples {
+        for channel in 0..3 {
+            mean[channel] += sample[channel] / count;
+        }
+    }
+
+    let mut covariance = [[0.0; 3]; 3];
+    for sample in &samples {
+        let deviation = difference(sample, &mean);
+        for row in 0..3 {
+            for col in 0..3 {
+                covariance[row][col] += deviation[row] * deviation[col] / (count - 1.0);
+            }
+        }
+    }
+
+    let inverse = invert(&covariance).unwrap_or([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+    across
+        .iter()
+        .map(|gradient| {
+            let deviation = difference(gradient, &mean);
+            (0..3)
+                .map(|row| (0..3).map(|col| deviation[row] * inverse[row][col] * deviation[col]).sum::<f64>())
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+fn invert(matrix: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let m = matrix;
+    let cofactor = |r1: usize, r2: usize, c1: usize, c2: usize| m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1];
+
+    let determinant = m[0][0] * cofactor(1, 2, 1, 2) - m[0][1] * cofactor(1, 2, 0, 2) + m[0][2] * cofactor(1, 2, 0, 1);
+    if determinant.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let adjugate = [
+        [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+        [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+        [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+    ];
+
+    Some(adjugate.map(|row| row.map(|value| value / determinant)))
+}
 
 pub fn calculate_dissimilarity(piece1: &DynamicImage, piece2: &DynamicImage, relation: char) -> f64 {
     let mut dissimilarity: f64 = 0.0;
@@ -13,12 +272,12 @@ pub fn calculate_dissimilarity(piece1: &DynamicImage, piece2: &DynamicImage, rel
 
 
             for i in 0..height {
-                let pixel1 = piece1.get_pixel(piece1_width-1, i);
-                let pixel2 = piece2.get_pixel(0, i);
+                let pixel1 = color_space::channels(piece1, piece1_width-1, i);
+                let pixel2 = color_space::channels(piece2, 0, i);
 
-                let diff_r = f64::from(pixel1[0]) - f64::from(pixel2[0]);
-                let diff_g = f64::from(pixel1[1]) - f64::from(pixel2[1]);
-                let diff_b = f64::from(pixel1[2]) - f64::from(pixel2[2]);
+                let diff_r = pixel1[0] - pixel2[0];
+                let diff_g = pixel1[1] - pixel2[1];
+                let diff_b = pixel1[2] - pixel2[2];
 
                 let current_diff = diff_r * diff_r + diff_g * diff_g + diff_b * diff_b;
                 dissimilarity += current_diff;
@@ -29,12 +288,12 @@ pub fn calculate_dissimilarity(piece1: &DynamicImage, piece2: &DynamicImage, rel
             let piece1_height = piece1.dimensions().1;
 
             for i in 0..width {
-                let pixel1 = piece1.get_pixel(i, piece1_height-1);
-                let pixel2 = piece2.get_pixel(i, 0);
+                let pixel1 = color_space::channels(piece1, i, piece1_height-1);
+                let pixel2 = color_space::channels(piece2, i, 0);
 
-                let diff_r = f64::from(pixel1[0]) - f64::from(pixel2[0]);
-                let diff_g = f64::from(pixel1[1]) - f64::from(pixel2[1]);
-                let diff_b = f64::from(pixel1[2]) - f64::from(pixel2[2]);
+                let diff_r = pixel1[0] - pixel2[0];
+                let diff_g = pixel1[1] - pixel2[1];
+                let diff_b = pixel1[2] - pixel2[2];
 
                 let current_diff = diff_r * diff_r + diff_g * diff_g + diff_b * diff_b;
                 dissimilarity += current_diff;
@@ -44,12 +303,12 @@ pub fn calculate_dissimilarity(piece1: &DynamicImage, piece2: &DynamicImage, rel
             let height = piece1.dimensions().1;
 
             for i in 0..height {
-                let pixel1 = piece1.get_pixel(0, i);
-                let pixel2 = piece2.get_pixel(piece2.dimensions().0-1, i);
+                let pixel1 = color_space::channels(piece1, 0, i);
+                let pixel2 = color_space::channels(piece2, piece2.dimensions().0-1, i);
 
-                let diff_r = f64::from(pixel1[0]) - f64::from(pixel2[0]);
-                let diff_g = f64::from(pixel1[1]) - f64::from(pixel2[1]);
-                let diff_b = f64::from(pixel1[2]) - f64::from(pixel2[2]);
+                let diff_r = pixel1[0] - pixel2[0];
+                let diff_g = pixel1[1] - pixel2[1];
+                let diff_b = pixel1[2] - pixel2[2];
 
                 let current_diff = diff_r * diff_r + diff_g * diff_g + diff_b * diff_b;
                 dissimilarity += current_diff;
@@ -59,12 +318,12 @@ pub fn calculate_dissimilarity(piece1: &DynamicImage, piece2: &DynamicImage, rel
             let width = piece1.dimensions().0;
 
             for i in 0..width {
-                let pixel1 = piece1.get_pixel(i, 0);
-                let pixel2 = piece2.get_pixel(i, piece2.dimensions().1-1);
+                let pixel1 = color_space::channels(piece1, i, 0);
+                let pixel2 = color_space::channels(piece2, i, piece2.dimensions().1-1);
 
-                let diff_r = f64::from(pixel1[0]) - f64::from(pixel2[0]);
-                let diff_g = f64::from(pixel1[1]) - f64::from(pixel2[1]);
-                let diff_b = f64::from(pixel1[2]) - f64::from(pixel2[2]);
+                let diff_r = pixel1[0] - pixel2[0];
+                let diff_g = pixel1[1] - pixel2[1];
+                let diff_b = pixel1[2] - pixel2[2];
 
                 let current_diff = diff_r * diff_r + diff_g * diff_g + diff_b * diff_b;
                 dissimilarity += current_diff;
@@ -78,20 +337,19 @@ pub fn calculate_dissimilarity(piece1: &DynamicImage, piece2: &DynamicImage, rel
     dissimilarity.sqrt()
 }
 
-pub fn calculate_dissimilarity_matrices(
-    loaded_pieces: &HashMap<String, DynamicImage>,
-) -> (HashMap<(String, String), f64>, HashMap<(String, String), f64>) {
-    let mut dissimilarity_matrix_r: HashMap<(String, String), f64> = HashMap::new();
-    let mut dissimilarity_matrix_d: HashMap<(String, String), f64> = HashMap::new();
+pub fn calculate_dissimilarity_matrices<P: GridProblem>(problem: &P) -> (DissimilarityMatrix, DissimilarityMatrix) {
+    let mut dissimilarity_matrix_r: DissimilarityMatrix = HashMap::new();
+    let mut dissimilarity_matrix_d: DissimilarityMatrix = HashMap::new();
 
+    let piece_keys = problem.piece_keys();
 
-    let dissimilarity_results: Vec<((String, String), f64, f64)> = loaded_pieces
+    let dissimilarity_results: Vec<((String, String), f64, f64)> = piece_keys
     .par_iter()
-    .flat_map(|(key1, piece1)| {
-        loaded_pieces.par_iter().filter_map(move |(key2, piece2)| {
+    .flat_map(|key1| {
+        piece_keys.par_iter().filter_map(move |key2| {
             if key1 != key2 {
-                let dissimilarity_r = calculate_dissimilarity(piece1, piece2, 'R');
-                let dissimilarity_d = calculate_dissimilarity(piece1, piece2, 'D');
+                let dissimilarity_r = problem.cost(key1, key2, 'R');
+                let dissimilarity_d = problem.cost(key1, key2, 'D');
                 Some(((key1.clone(), key2.clone()), dissimilarity_r, dissimilarity_d))
             } else {
                 None
@@ -104,4 +362,81 @@ pub fn calculate_dissimilarity_matrices(
     dissimilarity_matrix_d.extend(dissimilarity_results.iter().map(|&(ref k, _, v)| (k.clone(), v)));
 
     (dissimilarity_matrix_r, dissimilarity_matrix_d)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    fn piece(width: u32, height: u32, value: impl Fn(u32, u32) -> u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let value = value(x, y);
+            Rgb([value, value, value])
+        }))
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} is not close to {}", actual, expected);
+    }
+
+    #[test]
+    fn mgc_of_flat_pieces_follows_the_dummy_gradient_covariance() {
+        // Flat pieces only contribute zero gradients, so the covariance comes
+        // from the dummy gradients alone: (I + J) / 5 with mean zero. A seam
+        // step of d on all channels then costs 5 * (3 - 9 / 4) * d^2 per pixel.
+        let dark = piece(2, 2, |_, _| 10);
+        let light = piece(2, 2, |_, _| 20);
+
+        assert_close(Mgc.dissimilarity(&dark, &dark, 'R'), 0.0);
+        assert_close(Mgc.dissimilarity(&dark, &light, 'R'), 2.0 * 2.0 * 3.75 * 100.0);
+        assert_close(Mgc.dissimilarity(&light, &dark, 'L'), Mgc.dissimilarity(&dark, &light, 'R'));
+    }
+
+    #[test]
+    fn mgc_prefers_a_gradient_that_continues_across_the_seam() {
+        let ramp = piece(3, 3, |_, y| 10 * y as u8);
+        let continued = piece(3, 3, |_, y| 30 + 10 * y as u8);
+        let broken = piece(3, 3, |_, y| 60 + 10 * y as u8);
+
+        assert!(Mgc.dissimilarity(&ramp, &continued, 'D') < Mgc.dissimilarity(&ramp, &broken, 'D'));
+    }
+
+    #[test]
+    fn lpq_raises_the_summed_edge_differences_to_q_over_p() {
+        // Two edge pixels with three channels each differ by 10.
+        let dark = piece(2, 2, |_, _| 10);
+        let light = piece(2, 2, |_, _| 20);
+
+        assert_close(LpQ { p: 1.0, q: 1.0 }.dissimilarity(&dark, &light, 'R'), 60.0);
+        assert_close(LpQ { p: 2.0, q: 1.0 }.dissimilarity(&dark, &light, 'D'), 600.0_f64.sqrt());
+        assert_close(LpQ { p: 0.5, q: 2.0 }.dissimilarity(&dark, &light, 'R'), (6.0 * 10.0_f64.sqrt()).powi(4));
+        assert_close(LpQ { p: 0.3, q: 1.0 / 16.0 }.dissimilarity(&dark, &dark, 'R'), 0.0);
+    }
+
+    #[test]
+    fn prediction_extrapolates_both_pieces_across_the_seam() {
+        let ramp = piece(2, 2, |x, _| 10 * x as u8);
+        let continued = piece(2, 2, |x, _| 20 + 10 * x as u8);
+        let flat = piece(2, 2, |_, _| 20);
+
+        // The ramp predicts 20 for the flat piece's edge, but the flat piece
+        // predicts 20 instead of 10 for the ramp's edge: an error of 10 on
+        // three channels of two pixels.
+        assert_close(Prediction.dissimilarity(&ramp, &continued, 'R'), 0.0);
+        assert_close(Prediction.dissimilarity(&ramp, &flat, 'R'), 600.0_f64.sqrt());
+        assert_close(Prediction.dissimilarity(&flat, &ramp, 'L'), 600.0_f64.sqrt());
+    }
+
+    #[test]
+    fn metric_names_only_take_parameters_where_they_have_them() {
+        assert_eq!(DissimilarityMetric::from_name("lpq"), Some(DissimilarityMetric::LpQ(DEFAULT_P, DEFAULT_Q)));
+        assert_eq!(DissimilarityMetric::from_name("lpq:1,2"), Some(DissimilarityMetric::LpQ(1.0, 2.0)));
+        assert_eq!(DissimilarityMetric::from_name("mgc"), Some(DissimilarityMetric::Mgc));
+
+        for name in ["euclidean:5", "mgc:0.3,1", "prediction:", "lpq:1", "lpq:1,2,3", "lpq:0,1"] {
+            assert_eq!(DissimilarityMetric::from_name(name), None, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::placed_piece_count;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiversityStats {
+    pub unique_chromosomes: usize,
+    pub adjacency_diversity: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RestartPolicy {
+    pub stagnation_limit: usize,
+    pub min_diversity: f64,
+    pub fraction: f64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self { stagnation_limit: 0, min_diversity: 0.0, fraction: 0.5 }
+    }
+}
+
+impl RestartPolicy {
+    pub fn should_restart(&self, stagnant_generations: usize, diversity: &DiversityStats) -> bool {
+        let stagnated = self.stagnation_limit > 0 && stagnant_generations >= self.stagnation_limit;
+        let collapsed = diversity.adjacency_diversity < self.min_diversity;
+        stagnated || collapsed
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct StagnationTracker {
+    best_fitness: Option<f64>,
+    stagnant_generations: usize,
+}
+
+impl StagnationTracker {
+    pub fn update(&mut self, best_fitness: f64) -> usize {
+        match self.best_fitness {
+            Some(best) if best_fitness >= best => self.stagnant_generations += 1,
+            _ => {
+                self.best_fitness = Some(best_fitness);
+                self.stagnant_generations = 0;
+            }
+        }
+        self.stagnant_generations
+    }
+
+    pub fn reset(&mut self) {
+        self.stagnant_generations = 0;
+    }
+}
+
+pub fn measure_diversity(population: &[Vec<Vec<String>>]) -> DiversityStats {
+    let unique_chromosomes = population.iter().collect::<HashSet<_>>().len();
+
+    let mut distinct_pairs: HashSet<(&String, &String, char)> = HashSet::new();
+    let mut total_pairs = 0;
+
+    for chromosome in population {
+        for row in chromosome {
+            for pair in row.windows(2) {
+                if !pair[0].is_empty() && !pair[1].is_empty() {
+                    distinct_pairs.insert((&pair[0], &pair[1], 'R'));
+                    total_pairs += 1;
+                }
+            }
+        }
+        for rows in chromosome.windows(2) {
+            for (key1, key2) in rows[0].iter().zip(rows[1].iter()) {
+                if !key1.is_empty() && !key2.is_empty() {
+                    distinct_pairs.insert((key1, key2, 'D'));
+                    total_pairs += 1;
+                }
+            }
+        }
+    }
+
+    let pieces = population.first().map_or(0, |chromosome| placed_piece_count(chromosome));
+    let possible_pairs = 2 * pieces * pieces.saturating_sub(1);
+    let attainable_pairs = total_pairs.min(possible_pairs);
+
+    let adjacency_diversity = if attainable_pairs == 0 {
+        0.0
+    } else {
+        distinct_pairs.len() as f64 / attainable_pairs as f64
+    };
+
+    DiversityStats { unique_chromosomes, adjacency_diversity }
+}
+
+pub fn replace_duplicates<F: FnMut() -> Vec<Vec<String>>>(population: &mut [Vec<Vec<String>>], mut replacement: F) -> usize {
+    let mut seen: HashSet<u64> = HashSet::new();
+    let mut replaced = 0;
+
+    for chromosome in population.iter_mut() {
+        if seen.insert(chromosome_hash(chromosome)) {
+            continue;
+        }
+
+        *chromosome = replacement();
+        seen.insert(chromosome_hash(chromosome));
+        replaced += 1;
+    }
+
+    replaced
+}
+
+pub fn restart(population: &mut Vec<Vec<Vec<String>>>, protected: usize, fraction: f64, replacements: Vec<Vec<Vec<String>>>) -> usize {
+    let count = restart_count(population.len(), protected, fraction).min(replacements.len());
+    let start = population.len() - count;
+
+    population.truncate(start);
+    population.extend(replacements.into_iter().take(count));
+
+    count
+}
+
+pub fn restart_count(population_size: usize, protected: usize, fraction: f64) -> usize {
+    let replaceable = population_size.saturating_sub(protected);
+    ((replaceable as f64 * fraction).round() as usize).min(replaceable)
+}
+
+fn chromosome_hash(chromosome: &[Vec<String>]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    chromosome.hash(&mut hasher);
+    hasher.finish()
+}
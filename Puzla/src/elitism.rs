@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EliteSize {
+    Count(usize),
+    Percentage(f64),
+}
+
+impl EliteSize {
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(percentage) = name.strip_suffix('%') {
+            let percentage: f64 = percentage.parse().ok()?;
+            if !(0.0..=100.0).contains(&percentage) {
+                return None;
+            }
+            return Some(EliteSize::Percentage(percentage));
+        }
+
+        name.parse().ok().map(EliteSize::Count)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            EliteSize::Count(_) => true,
+            EliteSize::Percentage(percentage) => (0.0..=100.0).contains(&percentage),
+        }
+    }
+
+    pub fn resolve(&self, population_size: usize) -> usize {
+        let count = match *self {
+            EliteSize::Count(count) => count,
+            EliteSize::Percentage(percentage) => (population_size as f64 * percentage / 100.0).round() as usize,
+        };
+
+        count.min(population_size)
+    }
+}
+
+pub fn select_elite(population: &[Vec<Vec<String>>], sorted_fitness_scores: &[(usize, f64)], count: usize) -> Vec<Vec<Vec<String>>> {
+    let mut seen: HashSet<&Vec<Vec<String>>> = HashSet::new();
+    let mut elite = Vec::new();
+
+    for &(index, _) in sorted_fitness_scores {
+        if elite.len() == count {
+            break;
+        }
+
+        let chromosome = &population[index];
+        if seen.insert(chromosome) {
+            elite.push(chromosome.clone());
+        }
+    }
+
+    elite
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::observer::Observer;
+use crate::stats::GenerationStats;
+use crate::termination::StopReason;
+use crate::utils;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacedPiece {
+    pub piece: String,
+    pub row: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Solution {
+    pub width: usize,
+    pub height: usize,
+    pub piece_width: u32,
+    pub piece_height: u32,
+    pub pieces_directory: String,
+    pub fitness: f64,
+    pub accuracy: Option<f64>,
+    pub seed: u64,
+    pub generations: usize,
+    pub elapsed_seconds: f64,
+    pub stop_reason: String,
+    pub pieces: Vec<PlacedPiece>,
+    pub rejected: Vec<String>,
+}
+
+impl Solution {
+    pub fn chromosome(&self) -> Vec<Vec<String>> {
+        let mut chromosome = vec![vec![String::new(); self.width]; self.height];
+        for placed in &self.pieces {
+            if placed.row < self.height && placed.col < self.width {
+                chromosome[placed.row][placed.col] = placed.piece.clone();
+            }
+        }
+        chromosome
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = if is_csv(path) {
+            self.to_csv()
+        } else {
+            serde_json::to_string_pretty(self).map_err(|error| format!("Failed to serialize solution: {}", error))?
+        };
+
+        fs::write(path, contents).map_err(|error| format!("Failed to write solution {}: {}", path, error))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|error| format!("Failed to read solution {}: {}", path, error))?;
+
+        if is_csv(path) {
+            Self::from_csv(&contents).map_err(|error| format!("Failed to parse solution {}: {}", path, error))
+        } else {
+            serde_json::from_str(&contents).map_err(|error| format!("Failed to parse solution {}: {}", path, error))
+        }
+    }
+
+    pub fn render(&self, output_image: &str) -> Result<(), String> {
+        let loaded_pieces: HashMap<String, _> = utils::load_pieces(&self.pieces_directory)?
+            .iter()
+            .map(|(key, piece)| (key.clone(), utils::resize(piece, self.piece_width, self.piece_height)))
+            .collect();
+
+        if let Some(missing) = self.pieces.iter().find(|placed| !loaded_pieces.contains_key(&placed.piece)) {
+            return Err(format!("Piece {} not found in {}", missing.piece, self.pieces_directory));
+        }
+
+        let image_matrix = utils::create_image_matrix(&self.chromosome(), &loaded_pieces, (self.piece_width, self.piece_height));
+        let image = utils::create_image_from_matrix(&image_matrix);
+        image.save(output_image).map_err(|error| format!("Failed to save image {}: {}", output_image, error))
+    }
+
+    fn to_csv(&self) -> String {
+        let mut lines = vec![
+            format!("# width={}", self.width),
+            format!("# height={}", self.height),
+            format!("# piece_width={}", self.piece_width),
+            format!("# piece_height={}", self.piece_height),
+            format!("# pieces_directory={}", self.pieces_directory),
+            format!("# fitness={}", self.fitness),
+            format!("# accuracy={}", self.accuracy.map_or(String::new(), |accuracy| accuracy.to_string())),
+            format!("# seed={}", self.seed),
+            format!("# generations={}", self.generations),
+            format!("# elapsed_seconds={}", self.elapsed_seconds),
+            format!("# stop_reason={}", self.stop_reason),
+            format!("# rejected={}", self.rejected.join(&REJECTED_SEPARATOR.to_string())),
+            "piece,row,col".to_string(),
+        ];
+        lines.extend(self.pieces.iter().map(|placed| format!("{},{},{}", quote_csv_field(&placed.piece), placed.row, placed.col)));
+        lines.join("\n") + "\n"
+    }
+
+    fn from_csv(contents: &str) -> Result<Self, String> {
+        let mut metadata: HashMap<&str, &str> = HashMap::new();
+        let mut pieces = Vec::new();
+
+        let mut rest = contents;
+        while !rest.is_empty() {
+            if let Some(entry) = rest.strip_prefix("# ") {
+                let (line, next) = entry.split_once('\n').unwrap_or((entry, ""));
+                let line = line.strip_suffix('\r').unwrap_or(line);
+                let (key, value) = line.split_once('=').ok_or(format!("Invalid metadata line: # {}", line))?;
+                metadata.insert(key, value);
+                rest = next;
+                continue;
+            }
+
+            let (fields, next) = parse_csv_record(rest)?;
+            rest = next;
+            match fields.as_slice() {
+                [field] if field.trim().is_empty() => {}
+                [piece, row, col] if piece == "piece" && row == "row" && col == "col" => {}
+                [piece, row, col] => match (row.parse(), col.parse()) {
+                    (Ok(row), Ok(col)) => pieces.push(PlacedPiece { piece: piece.clone(), row, col }),
+                    _ => return Err(format!("Invalid piece row: {}", fields.join(","))),
+                },
+                _ => return Err(format!("Invalid piece row: {}", fields.join(","))),
+            }
+        }
+
+        let text = |key: &str| metadata.get(key).copied().ok_or(format!("Missing {}", key));
+        let number = |key: &str| -> Result<f64, String> { text(key)?.parse().map_err(|_| format!("Invalid {}", key)) };
+
+        Ok(Self {
+            width: number("width")? as usize,
+            height: number("height")? as usize,
+            piece_width: number("piece_width")? as u32,
+            piece_height: number("piece_height")? as u32,
+            pieces_directory: text("pieces_directory")?.to_string(),
+            fitness: number("fitness")?,
+            accuracy: number("accuracy").ok(),
+            seed: text("seed")?.parse().map_err(|_| "Invalid seed".to_string())?,
+            generations: number("generations")? as usize,
+            elapsed_seconds: number("elapsed_seconds")?,
+            stop_reason: text("stop_reason")?.to_string(),
+            pieces,
+            rejected: text("rejected").map(|rejected| rejected.split(REJECTED_SEPARATOR).filter(|key| !key.is_empty()).map(String::from).collect()).unwrap_or_default(),
+        })
+    }
+}
+
+pub struct SolutionExporter {
+    pub path: String,
+    pub pieces_directory: String,
+    pub piece_keys: Vec<String>,
+    pub piece_size: (u32, u32),
+    pub seed: u64,
+}
+
+impl Observer for SolutionExporter {
+    fn on_termination(&mut self, reason: &StopReason, stats: &GenerationStats, best: &[Vec<String>]) {
+        let pieces = best
+            .iter()
+            .enumerate()
+            .flat_map(|(row, cells)| cells.iter().enumerate().map(move |(col, piece)| (row, col, piece)))
+            .filter(|(_, _, piece)| !piece.is_empty())
+            .map(|(row, col, piece)| PlacedPiece { piece: piece.clone(), row, col })
+            .collect();
+
+        let (height, width) = utils::matrix_size(best);
+        let solution = Solution {
+            width,
+            height,
+            piece_width: self.piece_size.0,
+            piece_height: self.piece_size.1,
+            pieces_directory: self.pieces_directory.clone(),
+            fitness: stats.best_fitness,
+            accuracy: stats.accuracy,
+            seed: self.seed,
+            generations: stats.generation,
+            elapsed_seconds: stats.elapsed_seconds,
+            stop_reason: reason.to_string(),
+            pieces,
+            rejected: utils::rejected_pieces(best, &self.piece_keys),
+        };
+
+        solution.save(&self.path).unwrap_or_else(|error| eprintln!("{}", error));
+    }
+}
+
+// Piece keys are file names, which can hold commas but never a slash.
+const REJECTED_SEPARATOR: char = '/';
+
+// Quotes a field the way RFC 4180 does when it holds a separator, a quote
+// or a line break.
+fn quote_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Reads one RFC 4180 record and returns its fields with the input after it.
+fn parse_csv_record(input: &str) -> Result<(Vec<String>, &str), String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((index, character)) = chars.next() {
+        match character {
+            '"' if quoted => {
+                if chars.peek().map(|&(_, next)| next) == Some('"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\n' if !quoted => {
+                fields.push(field);
+                return Ok((fields, &input[index + 1..]));
+            }
+            '\r' if !quoted && chars.peek().map(|&(_, next)| next) == Some('\n') => {}
+            _ => field.push(character),
+        }
+    }
+
+    if quoted {
+        return Err("Unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok((fields, ""))
+}
+
+fn is_csv(path: &str) -> bool {
+    Path::new(path).extension().and_then(|extension| extension.to_str()) == Some("csv")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution() -> Solution {
+        Solution {
+            width: 2,
+            height: 1,
+            piece_width: 4,
+            piece_height: 4,
+            pieces_directory: "missing pieces".to_string(),
+            fitness: 12.5,
+            accuracy: Some(0.5),
+            seed: 9,
+            generations: 3,
+            elapsed_seconds: 0.25,
+            stop_reason: "reached the maximum of 3 generations".to_string(),
+            pieces: vec![
+                PlacedPiece { piece: "top, left.png".to_string(), row: 0, col: 0 },
+                PlacedPiece { piece: "a \"quoted\" piece.png".to_string(), row: 0, col: 1 },
+            ],
+            rejected: vec!["spare, one.png".to_string(), "spare.png".to_string()],
+        }
+    }
+
+    #[test]
+    fn csv_quotes_piece_names_with_separators_and_quotes() {
+        let csv = solution().to_csv();
+
+        assert!(csv.contains("\n\"top, left.png\",0,0\n"));
+        assert!(csv.contains("\n\"a \"\"quoted\"\" piece.png\",0,1\n"));
+    }
+
+    #[test]
+    fn csv_round_trips_piece_names_containing_commas() {
+        let path = std::env::temp_dir().join(format!("puzla-export-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        solution().save(path).unwrap();
+        let loaded = Solution::load(path);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.unwrap(), solution());
+    }
+
+    #[test]
+    fn rendering_without_the_pieces_directory_is_an_error() {
+        assert!(solution().render("unused.png").is_err());
+    }
+}
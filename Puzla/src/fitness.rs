@@ -2,13 +2,17 @@ use std::collections::HashMap;
 
 use rayon::prelude::*;
 
-pub fn evaluate_generation(generation: &Vec<Vec<Vec<String>>>, dissimilarities_r: &HashMap<(String, String), f64>, dissimilarities_d: &HashMap<(String, String), f64>) -> Vec<(usize, f64)>{
+use crate::utils;
+
+/// Scores every chromosome of a generation, returning `(index, fitness)`
+/// pairs in population order. Lower fitness is better.
+pub fn evaluate_generation(generation: &[Vec<Vec<String>>], dissimilarities_r: &HashMap<(String, String), f64>, dissimilarities_d: &HashMap<(String, String), f64>, empty_cell_penalty: f64, piece_count: usize) -> Vec<(usize, f64)>{
     
     let fitness_scores: Vec<(usize, f64)> = generation
         .par_iter() 
         .enumerate()
         .map(|(i, chromosome)| {
-            let current_fitness = calculate_fitness(chromosome, &dissimilarities_r, &dissimilarities_d);
+            let current_fitness = calculate_fitness(chromosome, dissimilarities_r, dissimilarities_d, empty_cell_penalty, piece_count);
             (i, current_fitness)
         })
         .collect();
@@ -16,18 +20,22 @@ pub fn evaluate_generation(generation: &Vec<Vec<Vec<String>>>, dissimilarities_r
     fitness_scores
 }
 
-pub fn calculate_fitness(chromosome: &Vec<Vec<String>>, dissimilarities_r: &HashMap<(String, String), f64>, dissimilarities_d: &HashMap<(String, String), f64>)  -> f64 {
-    let rows = chromosome.len();
-    let cols = chromosome.first().map_or(0, |row| row.len());
+/// Sums the right and down dissimilarities of all neighbouring pieces. A seam
+/// between a piece and an empty cell costs `empty_cell_penalty`, and so does
+/// every unplaced piece out of `piece_count` that a free cell could have held.
+pub fn calculate_fitness(chromosome: &[Vec<String>], dissimilarities_r: &HashMap<(String, String), f64>, dissimilarities_d: &HashMap<(String, String), f64>, empty_cell_penalty: f64, piece_count: usize)  -> f64 {
     let mut horizontal_dissimilarity = 0.0;
     let mut vertical_dissimilarity = 0.0;
 
     rayon::scope(|s| {
         s.spawn(|_| {
-            for i in 0..rows {
-                for j in 0..cols - 1 {
-                    let key1 = &chromosome[i][j];
-                    let key2 = &chromosome[i][j + 1];
+            for row in chromosome {
+                for pair in row.windows(2) {
+                    let (key1, key2) = (&pair[0], &pair[1]);
+                    if key1.is_empty() || key2.is_empty() {
+                        horizontal_dissimilarity += empty_seam_cost(key1, key2, empty_cell_penalty);
+                        continue;
+                    }
                     if let Some(&dissimilarity) =
                         dissimilarities_r.get(&(key1.clone(), key2.clone()))
                     {
@@ -43,10 +51,12 @@ pub fn calculate_fitness(chromosome: &Vec<Vec<String>>, dissimilarities_r: &Hash
         });
 
         s.spawn(|_| {
-            for i in 0..rows - 1 {
-                for j in 0..cols {
-                    let key1 = &chromosome[i][j];
-                    let key2 = &chromosome[i + 1][j];
+            for rows in chromosome.windows(2) {
+                for (key1, key2) in rows[0].iter().zip(rows[1].iter()) {
+                    if key1.is_empty() || key2.is_empty() {
+                        vertical_dissimilarity += empty_seam_cost(key1, key2, empty_cell_penalty);
+                        continue;
+                    }
                     if let Some(&dissimilarity) =
                         dissimilarities_d.get(&(key1.clone(), key2.clone()))
                     {
@@ -62,6 +72,89 @@ pub fn calculate_fitness(chromosome: &Vec<Vec<String>>, dissimilarities_r: &Hash
         });
     });
 
-    horizontal_dissimilarity + vertical_dissimilarity
+    horizontal_dissimilarity + vertical_dissimilarity + rejection_cost(chromosome, piece_count, empty_cell_penalty)
+}
+
+// Pieces beyond the number of cells have to be pooled, so they cost nothing
+// and fitness carries no constant offset. Every further pooled piece costs
+// one penalty on top of the up to four penalties of the seams around the
+// cell it leaves empty, so pooling only pays off for a piece whose own
+// seams would cost more than five penalties.
+fn rejection_cost(chromosome: &[Vec<String>], piece_count: usize, empty_cell_penalty: f64) -> f64 {
+    let cells: usize = chromosome.iter().map(|row| row.len()).sum();
+    let rejected = piece_count.saturating_sub(utils::placed_piece_count(chromosome));
+    let avoidable = rejected.saturating_sub(piece_count.saturating_sub(cells));
+    avoidable as f64 * empty_cell_penalty
+}
+
+/// Average dissimilarity over every known piece pair, the cost of a seam
+/// next to an empty cell.
+pub fn empty_cell_penalty(piece_keys: &[String], dissimilarities_r: &HashMap<(String, String), f64>, dissimilarities_d: &HashMap<(String, String), f64>) -> f64 {
+    let mut sorted_keys = piece_keys.to_vec();
+    sorted_keys.sort();
+
+    // Summed in key order rather than map order, so every process (including a
+    // resumed one) arrives at exactly the same penalty.
+    let mut total = 0.0;
+    let mut count = 0;
+    for key1 in &sorted_keys {
+        for key2 in &sorted_keys {
+            let pair = (key1.clone(), key2.clone());
+            for dissimilarity in [dissimilarities_r.get(&pair), dissimilarities_d.get(&pair)].into_iter().flatten() {
+                total += dissimilarity;
+                count += 1;
+            }
+        }
+    }
+
+    if count == 0 {
+        return 0.0;
+    }
+    total / count as f64
+}
+
+fn empty_seam_cost(key1: &str, key2: &str, empty_cell_penalty: f64) -> f64 {
+    if key1.is_empty() && key2.is_empty() {
+        0.0
+    } else {
+        empty_cell_penalty
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_cell_penalty_does_not_depend_on_map_order() {
+        let piece_keys: Vec<String> = (0..30).map(|i| i.to_string()).collect();
+        let pairs: Vec<((String, String), f64)> = piece_keys
+            .iter()
+            .enumerate()
+            .flat_map(|(i, first)| piece_keys.iter().map(move |second| ((first.clone(), second.clone()), 1.0 / (i as f64 + 3.0) + 1e6 * (i % 3) as f64)))
+            .collect();
+
+        // Every map gets its own hasher, so each one iterates in a different order.
+        let penalties: Vec<u64> = (0..5)
+            .map(|_| {
+                let right: HashMap<(String, String), f64> = pairs.iter().cloned().collect();
+                let down: HashMap<(String, String), f64> = pairs.iter().rev().cloned().collect();
+                empty_cell_penalty(&piece_keys, &right, &down).to_bits()
+            })
+            .collect();
+
+        assert!(penalties.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[test]
+    fn only_avoidable_pooling_is_charged() {
+        let grid = |cells: [&str; 4]| vec![vec![cells[0].to_string(), cells[1].to_string()], vec![cells[2].to_string(), cells[3].to_string()]];
+
+        // Six pieces for four cells: two always have to be pooled.
+        assert_eq!(rejection_cost(&grid(["a", "b", "c", "d"]), 6, 10.0), 0.0);
+        assert_eq!(rejection_cost(&grid(["a", "b", "c", ""]), 6, 10.0), 10.0);
+        // Three pieces for four cells: nothing has to be pooled.
+        assert_eq!(rejection_cost(&grid(["a", "b", "c", ""]), 3, 10.0), 0.0);
+        assert_eq!(rejection_cost(&grid(["a", "", "c", ""]), 3, 10.0), 10.0);
+    }
+}
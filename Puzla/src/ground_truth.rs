@@ -0,0 +1,109 @@
+use std::collections::{HashMap, HashSet};
+
+use image::{DynamicImage, GenericImageView};
+
+pub struct GroundTruth {
+    positions: HashMap<String, (usize, usize)>,
+    cells: usize,
+}
+
+impl GroundTruth {
+    pub fn from_reference(reference: &DynamicImage, pieces: &HashMap<String, DynamicImage>, width: usize, height: usize, pitch: (u32, u32)) -> Self {
+        let (pitch_x, pitch_y) = pitch;
+        let origins: Vec<((usize, usize), (u32, u32))> = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| ((row, col), (col as u32 * pitch_x, row as u32 * pitch_y)))
+            .filter(|&(_, (x, y))| x < reference.width() && y < reference.height())
+            .collect();
+
+        let mut candidates: Vec<(f64, &String, (usize, usize))> = pieces
+            .iter()
+            .flat_map(|(key, piece)| origins.iter().map(move |&(position, origin)| (mean_difference(piece, reference, origin), key, position)))
+            .collect();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(b.1)).then_with(|| a.2.cmp(&b.2)));
+
+        let mut positions = HashMap::new();
+        let mut used_cells = HashSet::new();
+        for (_, key, position) in candidates {
+            if positions.contains_key(key) || used_cells.contains(&position) {
+                continue;
+            }
+            used_cells.insert(position);
+            positions.insert(key.clone(), position);
+        }
+
+        Self { positions, cells: width * height }
+    }
+
+    pub fn is_correct(&self, chromosome: &[Vec<String>], row: usize, col: usize) -> bool {
+        chromosome
+            .get(row)
+            .and_then(|cells| cells.get(col))
+            .and_then(|key| self.positions.get(key))
+            == Some(&(row, col))
+    }
+
+    pub fn accuracy(&self, chromosome: &[Vec<String>]) -> f64 {
+        if self.cells == 0 {
+            return 0.0;
+        }
+
+        let correct = chromosome
+            .iter()
+            .enumerate()
+            .flat_map(|(row, cells)| (0..cells.len()).map(move |col| (row, col)))
+            .filter(|&(row, col)| self.is_correct(chromosome, row, col))
+            .count();
+
+        correct as f64 / self.cells as f64
+    }
+}
+
+fn mean_difference(piece: &DynamicImage, reference: &DynamicImage, origin: (u32, u32)) -> f64 {
+    let (x, y) = origin;
+    let width = piece.width().min(reference.width() - x);
+    let height = piece.height().min(reference.height() - y);
+    if width == 0 || height == 0 {
+        return f64::INFINITY;
+    }
+
+    let mut difference: u64 = 0;
+    for pixel_y in 0..height {
+        for pixel_x in 0..width {
+            let (a, b) = (piece.get_pixel(pixel_x, pixel_y), reference.get_pixel(x + pixel_x, y + pixel_y));
+            difference += (0..3).map(|channel| (a[channel] as i64 - b[channel] as i64).unsigned_abs()).sum::<u64>();
+        }
+    }
+
+    difference as f64 / (width * height) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    fn flat(width: u32, height: u32, value: impl Fn(u32) -> u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, _| {
+            let value = value(x);
+            Rgb([value, value, value])
+        }))
+    }
+
+    #[test]
+    fn near_duplicate_pieces_go_to_the_cells_that_fit_the_set_best() {
+        // Two cells of 100 and 110. On its own piece "a" (101) is closest to
+        // the first cell, but "b" (100) matches that cell exactly, so the
+        // one-to-one assignment leaves the second cell to "a".
+        let reference = flat(4, 2, |x| if x < 2 { 100 } else { 110 });
+        let pieces: HashMap<String, DynamicImage> = [("a".to_string(), flat(2, 2, |_| 101)), ("b".to_string(), flat(2, 2, |_| 100))].into_iter().collect();
+
+        let truth = GroundTruth::from_reference(&reference, &pieces, 2, 1, (2, 2));
+
+        let solved = vec![vec!["b".to_string(), "a".to_string()]];
+        let swapped = vec![vec!["a".to_string(), "b".to_string()]];
+        assert_eq!(truth.accuracy(&solved), 1.0);
+        assert_eq!(truth.accuracy(&swapped), 0.0);
+    }
+}
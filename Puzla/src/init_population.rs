@@ -1,13 +1,150 @@
-use crate::utils::create_random_matrix;
+use std::collections::{BTreeSet, HashSet, VecDeque};
 
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-pub fn generate_initial_population(pieces: Vec<String>, width: usize, height: usize, size: u32) -> Vec<Vec<Vec<String>>> {
+use crate::adjacency::AdjacencyData;
+use crate::utils::{adjacent_cells, create_random_matrix, opposite_relation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct SeedingRatios {
+    pub greedy: f64,
+    pub best_buddy: f64,
+}
+
+impl SeedingRatios {
+    pub fn is_valid(&self) -> bool {
+        (0.0..=1.0).contains(&self.greedy)
+            && (0.0..=1.0).contains(&self.best_buddy)
+            && self.greedy + self.best_buddy <= 1.0
+    }
+}
+
+pub fn generate_initial_population<R: Rng>(pieces: Vec<String>, width: usize, height: usize, size: usize, seeding: SeedingRatios, adjacency: &AdjacencyData, rng: &mut R) -> Vec<Vec<Vec<String>>> {
     let mut population: Vec<Vec<Vec<String>>> = Vec::new();
 
-    for _ in 0..size {
-        let current = create_random_matrix(&pieces, width, height);
+    let greedy_count = ((size as f64 * seeding.greedy).round() as usize).min(size);
+    let best_buddy_count = ((size as f64 * seeding.best_buddy).round() as usize).min(size - greedy_count);
+
+    for i in 0..size {
+        let current = if i < greedy_count {
+            create_greedy_matrix(&pieces, width, height, adjacency, rng)
+        } else if i < greedy_count + best_buddy_count {
+            create_best_buddy_matrix(&pieces, width, height, adjacency, rng)
+        } else {
+            create_random_matrix(&pieces, width, height, rng)
+        };
         population.push(current);
     }
-    
+
     population
-}
\ No newline at end of file
+}
+
+fn create_greedy_matrix<R: Rng>(pieces: &[String], width: usize, height: usize, adjacency: &AdjacencyData, rng: &mut R) -> Vec<Vec<String>> {
+    let mut matrix: Vec<Vec<String>> = vec![vec![String::new(); width]; height];
+    let mut available: HashSet<&String> = pieces.iter().collect();
+    let mut frontier: BTreeSet<(usize, usize)> = BTreeSet::new();
+
+    let root = match pieces.choose(rng) {
+        Some(root) => root,
+        None => return matrix,
+    };
+    let root_cell = (rng.gen_range(0..height), rng.gen_range(0..width));
+    place_piece(&mut matrix, &mut available, &mut frontier, root, root_cell);
+
+    while !available.is_empty() && !frontier.is_empty() {
+        let mut best: Option<(f64, (usize, usize), &String)> = None;
+
+        for &cell in &frontier {
+            for (relation, neighbour_cell) in adjacent_cells(cell, width, height) {
+                let neighbour = &matrix[neighbour_cell.0][neighbour_cell.1];
+                if neighbour.is_empty() {
+                    continue;
+                }
+
+                let most_compatible = adjacency.get_most_compatible(neighbour, opposite_relation(relation));
+                let candidate = most_compatible.iter().find(|(_, piece)| available.contains(piece));
+                if let Some((dissimilarity, piece)) = candidate {
+                    if best.is_none_or(|(best_dissimilarity, _, _)| *dissimilarity < best_dissimilarity) {
+                        best = Some((*dissimilarity, cell, piece));
+                    }
+                }
+            }
+        }
+
+        let (cell, piece) = match best {
+            Some((_, cell, piece)) => (cell, piece),
+            None => {
+                let cells: Vec<(usize, usize)> = frontier.iter().cloned().collect();
+                let mut remaining: Vec<&String> = available.iter().cloned().collect();
+                remaining.sort();
+                (*cells.choose(rng).unwrap(), *remaining.choose(rng).unwrap())
+            }
+        };
+        place_piece(&mut matrix, &mut available, &mut frontier, piece, cell);
+    }
+
+    matrix
+}
+
+fn create_best_buddy_matrix<R: Rng>(pieces: &[String], width: usize, height: usize, adjacency: &AdjacencyData, rng: &mut R) -> Vec<Vec<String>> {
+    let mut matrix: Vec<Vec<String>> = vec![vec![String::new(); width]; height];
+    let mut available: HashSet<&String> = pieces.iter().collect();
+    let mut frontier: BTreeSet<(usize, usize)> = BTreeSet::new();
+
+    let mut chain_starts: Vec<&String> = pieces.iter().collect();
+    chain_starts.shuffle(rng);
+    let mut empty_cells: Vec<(usize, usize)> = (0..height).flat_map(|row| (0..width).map(move |col| (row, col))).collect();
+    empty_cells.shuffle(rng);
+
+    for start in chain_starts {
+        if !available.contains(start) {
+            continue;
+        }
+
+        let start_cell = match empty_cells.iter().find(|&&(row, col)| matrix[row][col].is_empty()) {
+            Some(&cell) => cell,
+            None => break,
+        };
+        place_piece(&mut matrix, &mut available, &mut frontier, start, start_cell);
+
+        let mut chain: VecDeque<(&String, (usize, usize))> = VecDeque::from([(start, start_cell)]);
+        while let Some((piece, cell)) = chain.pop_front() {
+            for (relation, neighbour_cell) in adjacent_cells(cell, width, height) {
+                if !matrix[neighbour_cell.0][neighbour_cell.1].is_empty() {
+                    continue;
+                }
+
+                let buddy = adjacency.get_best_buddy_in_relation(piece, relation);
+                if let Some(buddy) = buddy.and_then(|buddy| available.get(&buddy).cloned()) {
+                    place_piece(&mut matrix, &mut available, &mut frontier, buddy, neighbour_cell);
+                    chain.push_back((buddy, neighbour_cell));
+                }
+            }
+        }
+    }
+
+    matrix
+}
+
+fn place_piece<'a>(
+    matrix: &mut [Vec<String>],
+    available: &mut HashSet<&'a String>,
+    frontier: &mut BTreeSet<(usize, usize)>,
+    piece: &'a String,
+    cell: (usize, usize),
+) {
+    let height = matrix.len();
+    let width = matrix[0].len();
+
+    matrix[cell.0][cell.1] = piece.clone();
+    available.remove(piece);
+    frontier.remove(&cell);
+
+    for (_, neighbour_cell) in adjacent_cells(cell, width, height) {
+        if matrix[neighbour_cell.0][neighbour_cell.1].is_empty() {
+            frontier.insert(neighbour_cell);
+        }
+    }
+}
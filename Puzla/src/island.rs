@@ -0,0 +1,257 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::adjacency::AdjacencyData;
+use crate::checkpoint::{IslandState, RngState};
+use crate::config::Config;
+use crate::dissimilarity::DissimilarityMatrix;
+use crate::diversity::{self, DiversityStats, StagnationTracker};
+use crate::elitism;
+use crate::fitness;
+use crate::init_population;
+use crate::mutation;
+use crate::selection::{ParentSelector, SelectionStrategy};
+use crate::utils;
+
+pub struct EvolutionContext<'a> {
+    pub config: &'a Config,
+    pub piece_keys: &'a [String],
+    pub width: usize,
+    pub height: usize,
+    pub adjacency: &'a AdjacencyData,
+    pub dissimilarity_r: &'a DissimilarityMatrix,
+    pub dissimilarity_d: &'a DissimilarityMatrix,
+    pub empty_cell_penalty: f64,
+}
+
+impl EvolutionContext<'_> {
+    pub fn evaluate(&self, population: &[Vec<Vec<String>>]) -> Vec<(usize, f64)> {
+        let mut fitness_scores = fitness::evaluate_generation(population, self.dissimilarity_r, self.dissimilarity_d, self.empty_cell_penalty, self.piece_keys.len());
+        fitness_scores.sort_by(|a, b| a.1.total_cmp(&b.1));
+        fitness_scores
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IslandSettings {
+    pub selection: SelectionStrategy,
+    pub mutation_rate: f64,
+    pub elite_count: usize,
+}
+
+impl IslandSettings {
+    pub fn for_island(config: &Config, id: usize) -> Self {
+        let selection = match config.island_selection.len() {
+            0 => config.selection,
+            len => config.island_selection[id % len],
+        };
+        let mutation_rate = match config.island_mutation.len() {
+            0 => config.mutation_rate,
+            len => config.island_mutation[id % len],
+        };
+
+        let elite_count = config.elitism.resolve(config.population_size);
+
+        Self { selection, mutation_rate, elite_count }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MigrationTopology {
+    Ring,
+    FullyConnected,
+}
+
+impl MigrationTopology {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ring" => Some(MigrationTopology::Ring),
+            "full" => Some(MigrationTopology::FullyConnected),
+            _ => None,
+        }
+    }
+}
+
+/// Individuals replaced by a restart during the last generation and why.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Restart {
+    pub count: usize,
+    pub stagnant_generations: usize,
+    pub adjacency_diversity: f64,
+}
+
+pub struct Island {
+    pub id: usize,
+    pub population: Vec<Vec<Vec<String>>>,
+    pub settings: IslandSettings,
+    /// Diversity of `population` as logged in the generation statistics,
+    /// refreshed by [`Island::measure_diversity`].
+    pub diversity: Option<DiversityStats>,
+    pub crossover_fallbacks: usize,
+    pub restart: Option<Restart>,
+    fitness_scores: Vec<(usize, f64)>,
+    rng: ChaCha8Rng,
+    stagnation: StagnationTracker,
+}
+
+impl Island {
+    pub fn new(id: usize, seed: u64, settings: IslandSettings, context: &EvolutionContext) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let population = init_population::generate_initial_population(
+            context.piece_keys.to_vec(),
+            context.width,
+            context.height,
+            context.config.population_size,
+            context.config.seeding,
+            context.adjacency,
+            &mut rng,
+        );
+
+        let fitness_scores = context.evaluate(&population);
+
+        Self { id, population, settings, diversity: None, crossover_fallbacks: 0, restart: None, fitness_scores, rng, stagnation: StagnationTracker::default() }
+    }
+
+    pub fn from_state(state: IslandState, context: &EvolutionContext) -> Self {
+        let fitness_scores = context.evaluate(&state.population);
+
+        Self {
+            id: state.id,
+            population: state.population,
+            settings: state.settings,
+            diversity: None,
+            crossover_fallbacks: 0,
+            restart: None,
+            fitness_scores,
+            rng: state.rng.restore(),
+            stagnation: state.stagnation,
+        }
+    }
+
+    pub fn to_state(&self) -> IslandState {
+        IslandState {
+            id: self.id,
+            population: self.population.clone(),
+            settings: self.settings,
+            stagnation: self.stagnation.clone(),
+            rng: RngState::capture(&self.rng),
+        }
+    }
+
+    pub fn evolve(&mut self, context: &EvolutionContext) {
+        let config = context.config;
+        let population_size = config.population_size;
+        let fitness_scores = std::mem::take(&mut self.fitness_scores);
+
+        let population_diversity = diversity::measure_diversity(&self.population);
+        let stagnant_generations = self.stagnation.update(fitness_scores[0].1);
+
+        let max_elite_count = 2 * config.elitism.resolve(population_size);
+        self.settings = config.adaptive.adapt(self.settings, stagnant_generations, &population_diversity, max_elite_count);
+
+        let elite = elitism::select_elite(&self.population, &fitness_scores, self.settings.elite_count);
+        let protected = elite.len();
+
+        let selector = ParentSelector::new(self.settings.selection, &fitness_scores);
+        let parent_pairs = selector.select_pairs(population_size.saturating_sub(elite.len()), &mut self.rng);
+        let seeds: Vec<u64> = parent_pairs.iter().map(|_| self.rng.gen()).collect();
+
+        let population = &self.population;
+        let mutation_rate = self.settings.mutation_rate;
+        let crossover = config.crossover.operator();
+        let (children, fallbacks): (Vec<Vec<Vec<String>>>, Vec<usize>) = parent_pairs.into_par_iter().zip(seeds).map(|((parent1_idx, parent2_idx), seed)| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let (mut child, fallbacks) = crossover.crossover(&population[parent1_idx], &population[parent2_idx], context.adjacency, &mut rng);
+            mutation::mutate(&mut child, context.piece_keys, mutation_rate, &mut rng);
+            (child, fallbacks)
+        }).unzip();
+
+        self.population = elite;
+        self.population.extend(children);
+        self.crossover_fallbacks = fallbacks.iter().sum();
+        self.restart = None;
+
+        if config.replace_duplicates {
+            let rng = &mut self.rng;
+            diversity::replace_duplicates(&mut self.population, || utils::create_random_matrix(context.piece_keys, context.width, context.height, rng));
+        }
+
+        if config.restart.should_restart(stagnant_generations, &population_diversity) {
+            let count = diversity::restart_count(self.population.len(), protected, config.restart.fraction);
+            let replacements = init_population::generate_initial_population(context.piece_keys.to_vec(), context.width, context.height, count, config.seeding, context.adjacency, &mut self.rng);
+            diversity::restart(&mut self.population, protected, config.restart.fraction, replacements);
+            self.stagnation.reset();
+            self.restart = Some(Restart { count, stagnant_generations, adjacency_diversity: population_diversity.adjacency_diversity });
+        }
+
+        self.fitness_scores = context.evaluate(&self.population);
+    }
+
+    /// Records the diversity of the current population, once duplicate
+    /// replacement, restarts and migration have changed it.
+    pub fn measure_diversity(&mut self) {
+        self.diversity = Some(diversity::measure_diversity(&self.population));
+    }
+
+    pub fn best(&self) -> Option<(&Vec<Vec<String>>, f64)> {
+        self.fitness_scores.first().map(|&(index, fitness)| (&self.population[index], fitness))
+    }
+
+    pub fn fitness_scores(&self) -> &[(usize, f64)] {
+        &self.fitness_scores
+    }
+
+    // Whether the fittest chromosomes of the population, as many as the elite
+    // count, are all the same arrangement. Only kept duplicates can make
+    // this happen, replaced ones never leave two copies in the population.
+    pub fn elites_identical(&self) -> bool {
+        let count = self.settings.elite_count.max(2);
+        if self.fitness_scores.len() < count {
+            return false;
+        }
+
+        let (first, _) = self.fitness_scores[0];
+        self.fitness_scores
+            .iter()
+            .take(count)
+            .all(|&(index, _)| self.population[index] == self.population[first])
+    }
+
+    fn emigrants(&self, count: usize) -> Vec<Vec<Vec<String>>> {
+        elitism::select_elite(&self.population, &self.fitness_scores, count)
+    }
+
+    fn receive(&mut self, immigrants: Vec<Vec<Vec<String>>>, context: &EvolutionContext) {
+        let worst = self.fitness_scores.iter().rev().map(|&(index, _)| index);
+
+        for (index, immigrant) in worst.zip(immigrants) {
+            self.population[index] = immigrant;
+        }
+
+        self.fitness_scores = context.evaluate(&self.population);
+    }
+}
+
+pub fn migrate(islands: &mut [Island], topology: MigrationTopology, migrants: usize, context: &EvolutionContext) {
+    if islands.len() < 2 || migrants == 0 {
+        return;
+    }
+
+    let emigrants: Vec<Vec<Vec<Vec<String>>>> = islands.par_iter().map(|island| island.emigrants(migrants)).collect();
+    let island_count = islands.len();
+
+    islands.par_iter_mut().enumerate().for_each(|(i, island)| {
+        let immigrants: Vec<Vec<Vec<String>>> = match topology {
+            MigrationTopology::Ring => emigrants[(i + island_count - 1) % island_count].clone(),
+            MigrationTopology::FullyConnected => emigrants
+                .iter()
+                .enumerate()
+                .filter(|&(source, _)| source != i)
+                .flat_map(|(_, chromosomes)| chromosomes.iter().cloned())
+                .collect(),
+        };
+        island.receive(immigrants, context);
+    });
+}
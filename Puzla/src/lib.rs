@@ -0,0 +1,247 @@
+//! Highly parallel jigsaw puzzle solver based on a genetic algorithm.
+//!
+//! The usual flow is to load a [`Puzzle`] from a directory of pieces and the
+//! original image, configure a [`Solver`] for its [`PreparedProblem`] through
+//! [`Solver::builder`], either [`Solver::run`] it or drive it with
+//! [`Solver::step`], and render the best chromosome with [`Puzzle::render`].
+//! [`run`] wires all of this together the way the command line tool does.
+//!
+//! The genetic algorithm itself only sees piece keys and directional costs,
+//! so any grid assignment problem implementing [`GridProblem`] can be solved
+//! the same way, for example a [`CostMatrixProblem`] built from known costs.
+//! Only the cost source is pluggable; chromosomes are always grids of
+//! `String` piece keys.
+
+use std::collections::HashMap;
+
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use rand::Rng;
+
+pub mod adaptive;
+pub mod adjacency;
+pub mod checkpoint;
+pub mod color_space;
+pub mod comparison;
+pub mod confidence;
+pub mod config;
+pub mod crossover;
+pub mod dissimilarity;
+pub mod diversity;
+pub mod elitism;
+pub mod export;
+pub mod fitness;
+pub mod ground_truth;
+pub mod init_population;
+pub mod island;
+pub mod mutation;
+pub mod observer;
+pub mod problem;
+pub mod report;
+pub mod selection;
+pub mod snapshot;
+pub mod solver;
+pub mod stats;
+pub mod termination;
+pub mod utils;
+
+pub use adjacency::AdjacencyData;
+pub use checkpoint::Checkpoint;
+pub use color_space::ColorSpace;
+pub use config::Config;
+pub use crossover::{CrossoverMethod, CrossoverOperator};
+pub use dissimilarity::{Dissimilarity, DissimilarityMatrix, DissimilarityMetric};
+pub use ground_truth::GroundTruth;
+pub use island::{EvolutionContext, Island};
+pub use observer::Observer;
+pub use problem::{CostMatrixProblem, GridProblem, ImageProblem, PreparedProblem};
+pub use solver::{RunResult, Solver, SolverBuilder};
+pub use stats::GenerationStats;
+pub use termination::StopReason;
+
+/// Puzzle pieces, the grid they are placed on and the compatibility data the
+/// genetic algorithm needs, computed once per puzzle.
+pub struct Puzzle {
+    /// The original image, used for the grid size and as the default reference.
+    pub original_image: DynamicImage,
+    /// Pieces as loaded from disk, keyed by file name.
+    pub original_pieces: HashMap<String, DynamicImage>,
+    /// Pieces resized to the average piece size; these are what gets placed.
+    pub pieces: HashMap<String, DynamicImage>,
+    /// Size of every piece in `pieces`.
+    pub piece_size: (u32, u32),
+    /// Largest original piece size, i.e. the distance between cells in the original image.
+    pub pitch: (u32, u32),
+    /// Grid size and edge costs of `pieces`, ready for the solver.
+    pub problem: PreparedProblem,
+}
+
+impl Puzzle {
+    /// Loads every piece in `pieces_directory` (skipping 1px slivers) and the
+    /// original image, then precomputes adjacency and dissimilarities with
+    /// `metric`, comparing pieces in `color_space`.
+    pub fn load(pieces_directory: &str, original_image: &str, metric: &dyn Dissimilarity, color_space: ColorSpace) -> Result<Self, String> {
+        let image = image::open(original_image).map_err(|error| format!("Failed to open original image {}: {}", original_image, error))?;
+        let pieces = utils::load_pieces(pieces_directory)?;
+        Self::new(image, pieces, metric, color_space)
+    }
+
+    /// Builds a puzzle from already loaded pieces and the original image.
+    pub fn new(original_image: DynamicImage, original_pieces: HashMap<String, DynamicImage>, metric: &dyn Dissimilarity, color_space: ColorSpace) -> Result<Self, String> {
+        let (
+            avg_width,
+            avg_height,
+            _smallest_width,
+            _smallest_height,
+            largest_width,
+            largest_height,
+        ) = utils::calculate_dimensions_stats(&original_pieces).ok_or("No pieces to solve")?;
+
+        let width = (original_image.dimensions().0 as f64 / avg_width as f64).round() as usize;
+        let height = (original_image.dimensions().1 as f64 / avg_height as f64).round() as usize;
+
+        let pieces: HashMap<String, DynamicImage> = original_pieces
+            .iter()
+            .map(|(key, piece)| (key.clone(), utils::resize(piece, avg_width, avg_height)))
+            .collect();
+
+        let compared_pieces: HashMap<String, DynamicImage> = pieces.iter().map(|(key, piece)| (key.clone(), color_space.convert(piece))).collect();
+        let problem = PreparedProblem::new(&ImageProblem { pieces: &compared_pieces, width, height, metric })?;
+
+        Ok(Self {
+            original_image,
+            original_pieces,
+            pieces,
+            piece_size: (avg_width, avg_height),
+            pitch: (largest_width, largest_height),
+            problem,
+        })
+    }
+
+    /// Finds where each original piece belongs in `reference`.
+    pub fn ground_truth(&self, reference: &DynamicImage) -> GroundTruth {
+        GroundTruth::from_reference(reference, &self.original_pieces, self.problem.width, self.problem.height, self.pitch)
+    }
+
+    /// Renders a chromosome into an image; empty cells stay transparent.
+    pub fn render(&self, chromosome: &[Vec<String>]) -> RgbaImage {
+        utils::create_image_from_matrix(&utils::create_image_matrix(chromosome, &self.pieces, self.piece_size))
+    }
+}
+
+/// Picks the seed for a run: the checkpoint's when resuming, then the
+/// configured one, otherwise a random seed.
+pub fn resolve_seed(config: &Config, resumed: Option<&Checkpoint>) -> u64 {
+    resumed
+        .map(|checkpoint| checkpoint.seed)
+        .or(config.seed)
+        .unwrap_or_else(|| rand::thread_rng().gen())
+}
+
+/// Runs the whole command line pipeline for `config`: loads the puzzle, sets
+/// up the observers the configuration asks for, solves it and saves the
+/// solved image to `config.output_image`. Progress is printed to stdout only
+/// when `verbose` is set.
+pub fn run(config: &Config, resumed: Option<Checkpoint>, verbose: bool) -> Result<RunResult, String> {
+    let puzzle = Puzzle::load(&config.pieces_directory, &config.original_image, config.dissimilarity.metric().as_ref(), config.color_space)?;
+
+    let problem = &puzzle.problem;
+    let cells = problem.width * problem.height;
+    let piece_count = problem.piece_keys.len();
+    if verbose && piece_count < cells {
+        println!("{} pieces for {} cells, {} cells will be left empty", piece_count, cells, cells - piece_count);
+    } else if verbose && piece_count > cells {
+        println!("{} pieces for {} cells, {} pieces will be rejected", piece_count, cells, piece_count - cells);
+    }
+
+    let comparison_requested = config.side_by_side.is_some() || config.difference.is_some();
+    let reference_image = match &config.ground_truth {
+        Some(path) => Some(image::open(path).map_err(|error| format!("Failed to open ground truth {}: {}", path, error))?),
+        None if comparison_requested => Some(puzzle.original_image.clone()),
+        None => None,
+    };
+    let ground_truth = reference_image.as_ref().map(|reference| puzzle.ground_truth(reference));
+
+    let seed = resolve_seed(config, resumed.as_ref());
+    let resuming = resumed.is_some();
+    let config_with_seed = Config { seed: Some(seed), ..config.clone() };
+
+    let mut observers: Vec<Box<dyn Observer + '_>> = Vec::new();
+    if let Some(path) = &config.stats {
+        let format = config.stats_format.unwrap_or_else(|| stats::StatsFormat::from_path(path));
+        observers.push(Box::new(stats::StatsLog::open(path, format, resuming)?));
+    }
+    if let Some(path) = &config.export {
+        observers.push(Box::new(export::SolutionExporter {
+            path: path.clone(),
+            pieces_directory: config.pieces_directory.clone(),
+            piece_keys: problem.piece_keys.clone(),
+            piece_size: puzzle.piece_size,
+            seed,
+        }));
+    }
+    if config.snapshot_interval > 0 {
+        let recorder = snapshot::SnapshotRecorder::new(&puzzle.pieces, puzzle.piece_size, &config.snapshot_directory, config.snapshot_interval, config.animation.clone(), config.frame_delay)?;
+        observers.push(Box::new(recorder));
+    }
+    if config.heatmap.is_some() || config.confidence_report.is_some() {
+        observers.push(Box::new(confidence::ConfidenceExporter {
+            pieces: &puzzle.pieces,
+            piece_size: puzzle.piece_size,
+            adjacency: &problem.adjacency,
+            dissimilarity_r: &problem.dissimilarity_r,
+            dissimilarity_d: &problem.dissimilarity_d,
+            metric: config.confidence_metric,
+            heatmap: config.heatmap.clone(),
+            report: config.confidence_report.clone(),
+        }));
+    }
+    if let (Some(reference), Some(truth)) = (&reference_image, &ground_truth) {
+        if comparison_requested {
+            observers.push(Box::new(comparison::ReferenceComparison {
+                pieces: &puzzle.original_pieces,
+                pitch: puzzle.pitch,
+                reference,
+                ground_truth: truth,
+                side_by_side: config.side_by_side.clone(),
+                difference: config.difference.clone(),
+            }));
+        }
+    }
+    if let Some(path) = &config.report {
+        observers.push(Box::new(report::HtmlReport {
+            path: path.clone(),
+            config,
+            seed,
+            pieces: &puzzle.pieces,
+            piece_size: puzzle.piece_size,
+            adjacency: &problem.adjacency,
+            dissimilarity_r: &problem.dissimilarity_r,
+            dissimilarity_d: &problem.dissimilarity_d,
+            history: Vec::new(),
+        }));
+    }
+
+    let mut builder = Solver::builder(problem).config(config_with_seed).verbose(verbose);
+    if let Some(checkpoint) = resumed {
+        builder = builder.resume(checkpoint);
+    }
+    if let Some(truth) = &ground_truth {
+        builder = builder.ground_truth(truth);
+    }
+    for observer in observers {
+        builder = builder.boxed_observer(observer);
+    }
+    let result = builder.build()?.run();
+
+    let rejected = utils::rejected_pieces(&result.best, &problem.piece_keys);
+    if verbose && !rejected.is_empty() {
+        println!("Rejected pieces: {}", rejected.join(", "));
+    }
+
+    puzzle
+        .render(&result.best)
+        .save(&config.output_image)
+        .map_err(|error| format!("Failed to save image {}: {}", config.output_image, error))?;
+
+    Ok(result)
+}
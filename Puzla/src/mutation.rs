@@ -0,0 +1,53 @@
+use rand::Rng;
+
+use crate::utils::{self, matrix_size};
+
+const POOL_SWAP_PROBABILITY: f64 = 0.5;
+
+/// With probability `mutation_rate` either swaps two cells or, when some of
+/// `piece_keys` are in the rejection pool, swaps a placed piece with a
+/// pooled one.
+pub fn mutate<R: Rng>(chromosome: &mut [Vec<String>], piece_keys: &[String], mutation_rate: f64, rng: &mut R) -> bool {
+    if mutation_rate <= 0.0 || !rng.gen_bool(mutation_rate.min(1.0)) {
+        return false;
+    }
+
+    let pool = utils::rejected_pieces(chromosome, piece_keys);
+    if !pool.is_empty() && rng.gen_bool(POOL_SWAP_PROBABILITY) {
+        return swap_with_pool(chromosome, &pool, rng);
+    }
+
+    let (rows, columns) = matrix_size(chromosome);
+    let cells = rows * columns;
+    if cells < 2 {
+        return false;
+    }
+
+    let first = rng.gen_range(0..cells);
+    let mut second = rng.gen_range(0..cells - 1);
+    if second >= first {
+        second += 1;
+    }
+
+    let first_piece = std::mem::take(&mut chromosome[first / columns][first % columns]);
+    let second_piece = std::mem::replace(&mut chromosome[second / columns][second % columns], first_piece);
+    chromosome[first / columns][first % columns] = second_piece;
+
+    true
+}
+
+fn swap_with_pool<R: Rng>(chromosome: &mut [Vec<String>], pool: &[String], rng: &mut R) -> bool {
+    let placed: Vec<(usize, usize)> = chromosome
+        .iter()
+        .enumerate()
+        .flat_map(|(row, cells)| cells.iter().enumerate().filter(|(_, piece)| !piece.is_empty()).map(move |(col, _)| (row, col)))
+        .collect();
+    if placed.is_empty() {
+        return false;
+    }
+
+    let (row, col) = placed[rng.gen_range(0..placed.len())];
+    chromosome[row][col] = pool[rng.gen_range(0..pool.len())].clone();
+
+    true
+}
@@ -0,0 +1,14 @@
+use crate::stats::GenerationStats;
+use crate::termination::StopReason;
+
+pub trait Observer {
+    fn on_generation_complete(&mut self, _stats: &GenerationStats, _best: &[Vec<String>]) {}
+
+    fn on_new_best(&mut self, _generation: usize, _fitness: f64, _best: &[Vec<String>]) {}
+
+    fn on_termination(&mut self, _reason: &StopReason, _stats: &GenerationStats, _best: &[Vec<String>]) {}
+
+    fn should_stop(&mut self, _stats: &GenerationStats) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use image::DynamicImage;
+
+use crate::adjacency::AdjacencyData;
+use crate::config::Config;
+use crate::dissimilarity::{self, Dissimilarity, DissimilarityMatrix};
+use crate::fitness;
+use crate::island::EvolutionContext;
+
+/// A grid assignment problem: place every piece on a `width` x `height` grid
+/// so that the summed cost of neighbouring pieces is as low as possible.
+///
+/// Pieces are identified by their keys, which is also what chromosomes hold.
+/// Only the cost source is pluggable: the genetic algorithm always evolves
+/// grids of `String` keys, with `""` marking an empty cell.
+pub trait GridProblem: Sync {
+    fn piece_keys(&self) -> Vec<String>;
+
+    /// Grid size as (width, height).
+    fn grid_size(&self) -> (usize, usize);
+
+    /// Cost of placing `second` directly right of (`'R'`) or below (`'D'`)
+    /// `first`. Lower is better. The solver only ever asks for these two
+    /// relations.
+    fn cost(&self, first: &str, second: &str, relation: char) -> f64;
+}
+
+/// Image tiles compared by `metric` along their touching edges.
+pub struct ImageProblem<'a> {
+    pub pieces: &'a HashMap<String, DynamicImage>,
+    pub width: usize,
+    pub height: usize,
+    pub metric: &'a dyn Dissimilarity,
+}
+
+impl GridProblem for ImageProblem<'_> {
+    fn piece_keys(&self) -> Vec<String> {
+        self.pieces.keys().cloned().collect()
+    }
+
+    fn grid_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn cost(&self, first: &str, second: &str, relation: char) -> f64 {
+        self.metric.dissimilarity(&self.pieces[first], &self.pieces[second], relation)
+    }
+}
+
+/// A problem given directly as right and down cost matrices. Left (`'L'`)
+/// and up (`'U'`) costs are read from them with the pieces swapped.
+pub struct CostMatrixProblem {
+    pub piece_keys: Vec<String>,
+    pub width: usize,
+    pub height: usize,
+    pub right: DissimilarityMatrix,
+    pub down: DissimilarityMatrix,
+    /// Cost of any pair missing from the matrices. Keep it finite, fitness
+    /// is the plain sum of seam costs.
+    pub missing_cost: f64,
+}
+
+impl GridProblem for CostMatrixProblem {
+    fn piece_keys(&self) -> Vec<String> {
+        self.piece_keys.clone()
+    }
+
+    fn grid_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn cost(&self, first: &str, second: &str, relation: char) -> f64 {
+        let (costs, pair) = match relation {
+            'R' => (&self.right, (first, second)),
+            'D' => (&self.down, (first, second)),
+            'L' => (&self.right, (second, first)),
+            'U' => (&self.down, (second, first)),
+            _ => panic!("Unknown relation {:?}, expected one of 'L', 'R', 'U', 'D'", relation),
+        };
+        costs.get(&(pair.0.to_string(), pair.1.to_string())).copied().unwrap_or(self.missing_cost)
+    }
+}
+
+/// Costs of a [`GridProblem`] precomputed into the lookups the genetic
+/// algorithm works with.
+pub struct PreparedProblem {
+    /// Sorted piece keys, so runs with the same seed are reproducible.
+    pub piece_keys: Vec<String>,
+    pub width: usize,
+    pub height: usize,
+    pub adjacency: AdjacencyData,
+    pub dissimilarity_r: DissimilarityMatrix,
+    pub dissimilarity_d: DissimilarityMatrix,
+    /// Fitness cost of a seam next to an empty cell.
+    pub empty_cell_penalty: f64,
+}
+
+impl PreparedProblem {
+    /// Precomputes the costs of `problem`. Keys must be unique and non-empty,
+    /// since an empty key is how chromosomes mark an empty cell.
+    pub fn new<P: GridProblem>(problem: &P) -> Result<Self, String> {
+        let mut piece_keys = problem.piece_keys();
+        piece_keys.sort();
+        if piece_keys.first().is_some_and(|key| key.is_empty()) {
+            return Err("Piece keys must not be empty, an empty key marks an empty cell".to_string());
+        }
+        if let Some(pair) = piece_keys.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("Duplicate piece key: {}", pair[0]));
+        }
+        let (width, height) = problem.grid_size();
+
+        let (dissimilarity_r, dissimilarity_d) = dissimilarity::calculate_dissimilarity_matrices(problem);
+        let adjacency = AdjacencyData::new(&piece_keys, &dissimilarity_r, &dissimilarity_d);
+        let empty_cell_penalty = fitness::empty_cell_penalty(&piece_keys, &dissimilarity_r, &dissimilarity_d);
+
+        Ok(Self { piece_keys, width, height, adjacency, dissimilarity_r, dissimilarity_d, empty_cell_penalty })
+    }
+
+    /// Everything a population needs to evolve on this problem with `config`.
+    pub fn context<'a>(&'a self, config: &'a Config) -> EvolutionContext<'a> {
+        EvolutionContext {
+            config,
+            piece_keys: &self.piece_keys,
+            width: self.width,
+            height: self.height,
+            adjacency: &self.adjacency,
+            dissimilarity_r: &self.dissimilarity_r,
+            dissimilarity_d: &self.dissimilarity_d,
+            empty_cell_penalty: self.empty_cell_penalty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(piece_keys: &[&str]) -> CostMatrixProblem {
+        CostMatrixProblem {
+            piece_keys: piece_keys.iter().map(|key| key.to_string()).collect(),
+            width: 2,
+            height: 1,
+            right: HashMap::new(),
+            down: HashMap::new(),
+            missing_cost: 1.0,
+        }
+    }
+
+    #[test]
+    fn keys_must_be_unique_and_non_empty() {
+        assert!(PreparedProblem::new(&problem(&["a", "b"])).is_ok());
+        assert!(PreparedProblem::new(&problem(&["a", ""])).is_err());
+        assert!(PreparedProblem::new(&problem(&["a", "b", "a"])).is_err());
+    }
+}
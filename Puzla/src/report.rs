@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+
+use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+
+use crate::adjacency::AdjacencyData;
+use crate::config::Config;
+use crate::confidence::{ConfidenceMetric, ConfidenceReport};
+use crate::dissimilarity::DissimilarityMatrix;
+use crate::observer::Observer;
+use crate::stats::GenerationStats;
+use crate::termination::StopReason;
+use crate::utils;
+
+const CHART_WIDTH: f64 = 720.0;
+const CHART_HEIGHT: f64 = 300.0;
+const CHART_MARGIN: f64 = 50.0;
+const WEAKEST_SEAMS: usize = 10;
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub struct HtmlReport<'a> {
+    pub path: String,
+    pub config: &'a Config,
+    pub seed: u64,
+    pub pieces: &'a HashMap<String, DynamicImage>,
+    pub piece_size: (u32, u32),
+    pub adjacency: &'a AdjacencyData,
+    pub dissimilarity_r: &'a DissimilarityMatrix,
+    pub dissimilarity_d: &'a DissimilarityMatrix,
+    pub history: Vec<GenerationStats>,
+}
+
+impl HtmlReport<'_> {
+    fn render(&self, reason: &StopReason, stats: &GenerationStats, best: &[Vec<String>]) -> Result<String, String> {
+        let solved = utils::create_image_from_matrix(&utils::create_image_matrix(best, self.pieces, self.piece_size));
+        let confidence = ConfidenceReport::new(best, self.config.confidence_metric, self.adjacency, self.dissimilarity_r, self.dissimilarity_d);
+        let config = serde_json::to_string_pretty(self.config).map_err(|error| format!("Failed to serialize configuration: {}", error))?;
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Puzla run report</title>\n<style>\n");
+        html.push_str("body { font-family: sans-serif; margin: 2em; color: #222; }\ntable { border-collapse: collapse; }\ntd, th { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\npre { background: #f4f4f4; padding: 1em; }\nimg { max-width: 100%; }\n");
+        html.push_str("</style>\n</head>\n<body>\n<h1>Puzla run report</h1>\n");
+
+        html.push_str("<h2>Summary</h2>\n<table>\n");
+        let mut summary = vec![
+            ("Seed", self.seed.to_string()),
+            ("Generations", stats.generation.to_string()),
+            ("Elapsed", format!("{:.1}s", stats.elapsed_seconds)),
+            ("Stop reason", reason.to_string()),
+            ("Best fitness", format!("{:.2}", stats.best_fitness)),
+            ("Grid", format!("{} x {}", utils::matrix_size(best).1, utils::matrix_size(best).0)),
+            ("Placed pieces", utils::placed_piece_count(best).to_string()),
+        ];
+        if let Some(accuracy) = stats.accuracy {
+            summary.push(("Accuracy", format!("{:.1}%", accuracy * 100.0)));
+        }
+        for (label, value) in summary {
+            let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, escape(&value));
+        }
+        html.push_str("</table>\n");
+
+        let _ = writeln!(html, "<h2>Solved image</h2>\n<img alt=\"solved puzzle\" src=\"data:image/png;base64,{}\">", encode_png(solved)?);
+
+        html.push_str("<h2>Convergence</h2>\n");
+        html.push_str(&self.convergence_chart());
+
+        html.push_str("<h2>Seam confidence</h2>\n<table>\n");
+        let metric = match confidence.metric {
+            ConfidenceMetric::Dissimilarity => "dissimilarity",
+            ConfidenceMetric::Rank => "rank",
+        };
+        let low_confidence = confidence.seams.iter().filter(|seam| seam.confidence < 0.5).count();
+        let _ = writeln!(html, "<tr><th>Metric</th><td>{}</td></tr>", metric);
+        let _ = writeln!(html, "<tr><th>Seams</th><td>{}</td></tr>", confidence.seams.len());
+        let _ = writeln!(html, "<tr><th>Mean confidence</th><td>{:.3}</td></tr>", confidence.mean_confidence);
+        let _ = writeln!(html, "<tr><th>Seams below 0.5</th><td>{}</td></tr>", low_confidence);
+        html.push_str("</table>\n<h3>Weakest seams</h3>\n<table>\n<tr><th>Cell</th><th>Piece</th><th>Neighbour</th><th>Relation</th><th>Dissimilarity</th><th>Rank</th><th>Confidence</th></tr>\n");
+
+        let mut weakest: Vec<_> = confidence.seams.iter().collect();
+        weakest.sort_by(|a, b| a.confidence.total_cmp(&b.confidence));
+        for seam in weakest.iter().take(WEAKEST_SEAMS) {
+            let _ = writeln!(
+                html,
+                "<tr><td>({}, {})</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td><td>{}</td><td>{:.3}</td></tr>",
+                seam.first_cell.0,
+                seam.first_cell.1,
+                escape(&seam.first),
+                escape(&seam.second),
+                seam.relation,
+                seam.dissimilarity,
+                seam.rank.map_or("-".to_string(), |rank| rank.to_string()),
+                seam.confidence
+            );
+        }
+        html.push_str("</table>\n");
+
+        let _ = writeln!(html, "<h2>Configuration</h2>\n<pre>{}</pre>", escape(&config));
+        html.push_str("</body>\n</html>\n");
+
+        Ok(html)
+    }
+
+    fn convergence_chart(&self) -> String {
+        if self.history.is_empty() {
+            return "<p>No generations recorded.</p>\n".to_string();
+        }
+
+        let first_generation = self.history[0].generation as f64;
+        let last_generation = self.history[self.history.len() - 1].generation as f64;
+        let generation_span = (last_generation - first_generation).max(1.0);
+        let highest = self.history.iter().map(|stats| stats.mean_fitness).fold(f64::NEG_INFINITY, f64::max);
+        let lowest = self.history.iter().map(|stats| stats.best_fitness).fold(f64::INFINITY, f64::min);
+        let fitness_span = (highest - lowest).max(f64::EPSILON);
+
+        let plot_width = CHART_WIDTH - 2.0 * CHART_MARGIN;
+        let plot_height = CHART_HEIGHT - 2.0 * CHART_MARGIN;
+        let x = |generation: usize| CHART_MARGIN + (generation as f64 - first_generation) / generation_span * plot_width;
+        let y = |fitness: f64| CHART_MARGIN + (highest - fitness) / fitness_span * plot_height;
+        let polyline = |value: fn(&GenerationStats) -> f64| {
+            self.history
+                .iter()
+                .map(|stats| format!("{:.1},{:.1}", x(stats.generation), y(value(stats))))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">", CHART_WIDTH, CHART_HEIGHT);
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{m}\" y=\"{m}\" width=\"{w}\" height=\"{h}\" fill=\"none\" stroke=\"#999\"/>",
+            m = CHART_MARGIN,
+            w = plot_width,
+            h = plot_height
+        );
+        let _ = writeln!(svg, "<polyline fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"2\" points=\"{}\"/>", polyline(|stats| stats.best_fitness));
+        let _ = writeln!(svg, "<polyline fill=\"none\" stroke=\"#ff7f0e\" stroke-width=\"2\" points=\"{}\"/>", polyline(|stats| stats.mean_fitness));
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"12\">{:.0}</text>", 4.0, CHART_MARGIN + 4.0, highest);
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"12\">{:.0}</text>", 4.0, CHART_HEIGHT - CHART_MARGIN + 4.0, lowest);
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"12\">generation {}</text>", CHART_MARGIN, CHART_HEIGHT - CHART_MARGIN + 20.0, first_generation);
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"end\">generation {}</text>",
+            CHART_WIDTH - CHART_MARGIN,
+            CHART_HEIGHT - CHART_MARGIN + 20.0,
+            last_generation
+        );
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"12\" fill=\"#1f77b4\">best fitness</text>", CHART_MARGIN, CHART_MARGIN - 10.0);
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"12\" fill=\"#ff7f0e\">mean fitness</text>", CHART_MARGIN + 120.0, CHART_MARGIN - 10.0);
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+}
+
+impl Observer for HtmlReport<'_> {
+    fn on_generation_complete(&mut self, stats: &GenerationStats, _best: &[Vec<String>]) {
+        self.history.push(*stats);
+    }
+
+    fn on_termination(&mut self, reason: &StopReason, stats: &GenerationStats, best: &[Vec<String>]) {
+        let result = self
+            .render(reason, stats, best)
+            .and_then(|html| fs::write(&self.path, html).map_err(|error| format!("Failed to write report {}: {}", self.path, error)));
+
+        result.unwrap_or_else(|error| eprintln!("{}", error));
+    }
+}
+
+fn encode_png(image: RgbaImage) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut bytes, ImageOutputFormat::Png)
+        .map_err(|error| format!("Failed to encode image: {}", error))?;
+    Ok(base64(&bytes))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let block = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(block >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_the_rfc_4648_test_vectors() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe, 0x00, 0x3e, 0x3f]), "//4APj8=");
+    }
+}
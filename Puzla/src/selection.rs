@@ -1,32 +1,329 @@
 use std::vec::Vec;
 
+use rand::seq::SliceRandom;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-pub fn select_parents(population: &Vec<(usize, f64)>) -> (usize, usize) {
-    let parent1 = choose_one(&population).unwrap();
-    let parent2 = choose_one(&population).unwrap();
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SelectionStrategy {
+    /// Fitness proportional, with weights `(1 / fitness)^exponent`.
+    Roulette(f64),
+    Tournament(usize),
+    Rank(f64),
+    /// Same weights as `Roulette`, sampled with evenly spaced pointers.
+    StochasticUniversal(f64),
+}
+
+impl SelectionStrategy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mut parts = name.splitn(2, ':');
+        let strategy = parts.next()?;
+        let parameter = parts.next();
+
+        match strategy {
+            "roulette" => Some(SelectionStrategy::Roulette(parse_exponent(parameter)?)),
+            "tournament" => {
+                let size = parameter.map_or(Some(3), |p| p.parse().ok())?;
+                if size == 0 {
+                    return None;
+                }
+                Some(SelectionStrategy::Tournament(size))
+            }
+            "rank" => {
+                let pressure = parameter.map_or(Some(1.5), |p| p.parse().ok())?;
+                if !(1.0..=2.0).contains(&pressure) {
+                    return None;
+                }
+                Some(SelectionStrategy::Rank(pressure))
+            }
+            "sus" => Some(SelectionStrategy::StochasticUniversal(parse_exponent(parameter)?)),
+            _ => None,
+        }
+    }
+
+    /// Whether the parameter is in the range `from_name` accepts.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            SelectionStrategy::Roulette(exponent) | SelectionStrategy::StochasticUniversal(exponent) => exponent > 0.0 && exponent <= MAX_FITNESS_EXPONENT,
+            SelectionStrategy::Tournament(size) => size > 0,
+            SelectionStrategy::Rank(pressure) => (1.0..=2.0).contains(&pressure),
+        }
+    }
+
+    /// The parameter adaptive control raises or lowers: the fitness exponent,
+    /// tournament size or rank pressure.
+    pub fn pressure(&self) -> f64 {
+        match *self {
+            SelectionStrategy::Roulette(exponent) | SelectionStrategy::StochasticUniversal(exponent) => exponent,
+            SelectionStrategy::Tournament(size) => size as f64,
+            SelectionStrategy::Rank(pressure) => pressure,
+        }
+    }
+
+    pub fn with_adjusted_pressure(self, steps: i32) -> Self {
+        match self {
+            SelectionStrategy::Tournament(size) => {
+                let min_size = MIN_ADAPTED_TOURNAMENT_SIZE.min(size as i32);
+                SelectionStrategy::Tournament((size as i32 + steps).clamp(min_size, MAX_TOURNAMENT_SIZE) as usize)
+            }
+            SelectionStrategy::Rank(pressure) => {
+                SelectionStrategy::Rank((pressure + RANK_PRESSURE_STEP * steps as f64).clamp(1.0, 2.0))
+            }
+            SelectionStrategy::Roulette(exponent) => SelectionStrategy::Roulette(adjusted_exponent(exponent, steps)),
+            SelectionStrategy::StochasticUniversal(exponent) => SelectionStrategy::StochasticUniversal(adjusted_exponent(exponent, steps)),
+        }
+    }
+}
+
+fn parse_exponent(parameter: Option<&str>) -> Option<f64> {
+    let exponent = parameter.map_or(Some(1.0), |p| p.parse().ok())?;
+    if !(exponent > 0.0 && exponent <= MAX_FITNESS_EXPONENT) {
+        return None;
+    }
+    Some(exponent)
+}
+
+fn adjusted_exponent(exponent: f64, steps: i32) -> f64 {
+    let min_exponent = MIN_ADAPTED_FITNESS_EXPONENT.min(exponent);
+    (exponent + FITNESS_EXPONENT_STEP * steps as f64).clamp(min_exponent, MAX_FITNESS_EXPONENT)
+}
+
+const MIN_ADAPTED_TOURNAMENT_SIZE: i32 = 2;
+const MAX_TOURNAMENT_SIZE: i32 = 16;
+const RANK_PRESSURE_STEP: f64 = 0.1;
+const MIN_ADAPTED_FITNESS_EXPONENT: f64 = 0.5;
+const MAX_FITNESS_EXPONENT: f64 = 8.0;
+const FITNESS_EXPONENT_STEP: f64 = 0.25;
+
+/// Picks parent pairs from one generation's fitness scores with a
+/// `SelectionStrategy`, never pairing an individual with itself when the
+/// population has a choice.
+pub struct ParentSelector {
+    strategy: SelectionStrategy,
+    population: Vec<(usize, f64)>,
+    cumulative: Vec<f64>,
+}
+
+impl ParentSelector {
+    /// Ranks `fitness_scores` (as `(index, fitness)` pairs) and precomputes
+    /// the selection weights `strategy` needs.
+    pub fn new(strategy: SelectionStrategy, fitness_scores: &[(usize, f64)]) -> Self {
+        let mut population = fitness_scores.to_vec();
+        population.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let weights: Vec<f64> = match strategy {
+            SelectionStrategy::Roulette(exponent) | SelectionStrategy::StochasticUniversal(exponent) => population
+                .iter()
+                .map(|&(_, fitness)| (1.0 / fitness.max(f64::EPSILON)).powf(exponent))
+                .collect(),
+            SelectionStrategy::Rank(pressure) => rank_weights(population.len(), pressure),
+            SelectionStrategy::Tournament(_) => Vec::new(),
+        };
+
+        let cumulative = weights
+            .iter()
+            .scan(0.0, |total, weight| {
+                *total += weight;
+                Some(*total)
+            })
+            .collect();
+
+        Self { strategy, population, cumulative }
+    }
+
+    /// Returns `count` pairs of population indices to recombine.
+    pub fn select_pairs<R: Rng>(&self, count: usize, rng: &mut R) -> Vec<(usize, usize)> {
+        if self.population.is_empty() || count == 0 {
+            return Vec::new();
+        }
+
+        if let (SelectionStrategy::StochasticUniversal(_), Some(total)) = (self.strategy, self.total_weight()) {
+            return self.stochastic_universal_pairs(count, total, rng);
+        }
+
+        (0..count)
+            .map(|_| {
+                let parent1 = self.choose_one(rng);
+                let parent2 = self.choose_other(parent1, rng);
+                (parent1, parent2)
+            })
+            .collect()
+    }
+
+    fn choose_one<R: Rng>(&self, rng: &mut R) -> usize {
+        match self.strategy {
+            SelectionStrategy::Tournament(size) => {
+                (0..size)
+                    .map(|_| rng.gen_range(0..self.population.len()))
+                    .min()
+                    .map(|position| self.population[position].0)
+                    .unwrap()
+            }
+            _ => match self.total_weight() {
+                Some(total) => self.sample_cumulative(rng.gen_range(0.0..total)),
+                None => self.population[rng.gen_range(0..self.population.len())].0,
+            },
+        }
+    }
+
+    // Sum of all selection weights, or None when they cannot be sampled
+    // from and parents are picked uniformly instead.
+    fn total_weight(&self) -> Option<f64> {
+        self.cumulative.last().copied().filter(|&total| total > 0.0 && total.is_finite())
+    }
+
+    fn choose_other<R: Rng>(&self, parent: usize, rng: &mut R) -> usize {
+        if self.population.len() < 2 {
+            return parent;
+        }
+
+        for _ in 0..MAX_RESELECTION_ATTEMPTS {
+            let other = self.choose_one(rng);
+            if other != parent {
+                return other;
+            }
+        }
+
+        loop {
+            let (other, _) = self.population[rng.gen_range(0..self.population.len())];
+            if other != parent {
+                return other;
+            }
+        }
+    }
+
+    fn sample_cumulative(&self, point: f64) -> usize {
+        let position = self.cumulative.partition_point(|&cumulative| cumulative < point);
+        self.population[position.min(self.population.len() - 1)].0
+    }
+
+    fn stochastic_universal_pairs<R: Rng>(&self, count: usize, total: f64, rng: &mut R) -> Vec<(usize, usize)> {
+        let pointers = count * 2;
+        let distance = total / pointers as f64;
+        let start = rng.gen_range(0.0..distance);
+
+        let mut mating_pool: Vec<usize> = (0..pointers)
+            .map(|i| self.sample_cumulative(start + i as f64 * distance))
+            .collect();
+        mating_pool.shuffle(rng);
 
-    (parent1, parent2)
+        for i in (0..pointers).step_by(2) {
+            if mating_pool[i] != mating_pool[i + 1] {
+                continue;
+            }
+            if let Some(j) = (0..pointers).find(|&j| j / 2 != i / 2 && mating_pool[j] != mating_pool[i] && mating_pool[j ^ 1] != mating_pool[i + 1]) {
+                mating_pool.swap(i + 1, j);
+            }
+        }
+
+        mating_pool.chunks(2).map(|pair| (pair[0], pair[1])).collect()
+    }
+}
+
+const MAX_RESELECTION_ATTEMPTS: usize = 16;
+
+fn rank_weights(size: usize, pressure: f64) -> Vec<f64> {
+    if size < 2 {
+        return vec![1.0; size];
+    }
+
+    let n = size as f64;
+    (0..size)
+        .map(|position| {
+            let rank = (size - 1 - position) as f64;
+            (2.0 - pressure) / n + 2.0 * rank * (pressure - 1.0) / (n * (n - 1.0))
+        })
+        .collect()
 }
 
-fn choose_one(population: &Vec<(usize, f64)>) -> Option<usize> {
-    let inverted_fitness: Vec<(usize, f64)> = population
-        .iter()
-        .map(|&(id, fitness)| (id, 1.0 / fitness))
-        .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    const SAMPLES: usize = 70_000;
+
+    fn fitness_scores() -> Vec<(usize, f64)> {
+        vec![(0, 1.0), (1, 2.0), (2, 4.0)]
+    }
+
+    fn shares(strategy: SelectionStrategy) -> Vec<f64> {
+        let selector = ParentSelector::new(strategy, &fitness_scores());
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut counts = [0usize; 3];
+        for _ in 0..SAMPLES {
+            counts[selector.choose_one(&mut rng)] += 1;
+        }
+        counts.iter().map(|&count| count as f64 / SAMPLES as f64).collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual - expected).abs() < 0.01, "{:?} is not close to {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn roulette_is_proportional_to_inverse_fitness() {
+        assert_close(&shares(SelectionStrategy::Roulette(1.0)), &[4.0 / 7.0, 2.0 / 7.0, 1.0 / 7.0]);
+        assert_close(&shares(SelectionStrategy::Roulette(2.0)), &[16.0 / 21.0, 4.0 / 21.0, 1.0 / 21.0]);
+    }
+
+    #[test]
+    fn rank_follows_linear_ranking() {
+        let weights = rank_weights(3, 1.5);
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert_close(&shares(SelectionStrategy::Rank(1.5)), &[0.5, 1.0 / 3.0, 1.0 / 6.0]);
+    }
+
+    #[test]
+    fn tournament_picks_the_best_of_its_contestants() {
+        assert_close(&shares(SelectionStrategy::Tournament(1)), &[1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0]);
+        assert_close(&shares(SelectionStrategy::Tournament(2)), &[5.0 / 9.0, 3.0 / 9.0, 1.0 / 9.0]);
+    }
 
-    let total_inverted_fitness: f64 = inverted_fitness.iter().map(|&(_, fitness)| fitness).sum();
-    let mut rng = rand::thread_rng();
-    let random_number = rng.gen_range(0.0..total_inverted_fitness);
+    #[test]
+    fn stochastic_universal_gives_each_individual_its_expected_count() {
+        let selector = ParentSelector::new(SelectionStrategy::StochasticUniversal(1.0), &fitness_scores());
+        let pairs = selector.select_pairs(700, &mut ChaCha8Rng::seed_from_u64(1));
+        assert_eq!(pairs.len(), 700);
 
-    let mut cumulative_inverted_fitness = 0.0;
+        let mut counts = [0i64; 3];
+        for &(parent1, parent2) in &pairs {
+            counts[parent1] += 1;
+            counts[parent2] += 1;
+        }
+        for (count, expected) in counts.iter().zip([800, 400, 200]) {
+            assert!((count - expected).abs() <= 1, "{:?}", counts);
+        }
+    }
 
-    for &(id, inverted_fitness) in &inverted_fitness {
-        cumulative_inverted_fitness += inverted_fitness;
-        if cumulative_inverted_fitness >= random_number {
-            return Some(id);
+    #[test]
+    fn parents_differ_even_when_one_dominates() {
+        let dominated = vec![(0, 1e-9), (1, 1e3), (2, 1e3)];
+        for strategy in [SelectionStrategy::Roulette(1.0), SelectionStrategy::Rank(2.0), SelectionStrategy::Tournament(5)] {
+            let selector = ParentSelector::new(strategy, &dominated);
+            let pairs = selector.select_pairs(500, &mut ChaCha8Rng::seed_from_u64(2));
+            assert!(pairs.iter().all(|(parent1, parent2)| parent1 != parent2), "{:?}", strategy);
         }
+
+        // Distinct pairs are only possible while nobody holds more than half of the pointers.
+        let balanced = vec![(0, 1.0), (1, 1.5), (2, 2.0)];
+        let selector = ParentSelector::new(SelectionStrategy::StochasticUniversal(1.0), &balanced);
+        let pairs = selector.select_pairs(500, &mut ChaCha8Rng::seed_from_u64(2));
+        assert!(pairs.iter().all(|(parent1, parent2)| parent1 != parent2));
     }
 
-    None
+    #[test]
+    fn selecting_no_pairs_or_from_unusable_weights_does_not_panic() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for strategy in [SelectionStrategy::Roulette(1.0), SelectionStrategy::StochasticUniversal(1.0)] {
+            assert!(ParentSelector::new(strategy, &fitness_scores()).select_pairs(0, &mut rng).is_empty());
+
+            let infinite = vec![(0, f64::INFINITY), (1, f64::INFINITY)];
+            let pairs = ParentSelector::new(strategy, &infinite).select_pairs(10, &mut rng);
+            assert_eq!(pairs.len(), 10);
+        }
+    }
 }
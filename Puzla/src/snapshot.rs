@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame};
+
+use crate::observer::Observer;
+use crate::stats::GenerationStats;
+use crate::termination::StopReason;
+use crate::utils;
+
+const FRAME_PREFIX: &str = "generation_";
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+pub struct SnapshotRecorder<'a> {
+    pieces: &'a HashMap<String, DynamicImage>,
+    piece_size: (u32, u32),
+    directory: String,
+    interval: usize,
+    animation: Option<String>,
+    frame_delay: u32,
+    last_snapshot: usize,
+    // Frames saved by this recorder, so stale frames left in a shared directory stay out of the animation.
+    frames: Vec<PathBuf>,
+}
+
+impl<'a> SnapshotRecorder<'a> {
+    pub fn new(pieces: &'a HashMap<String, DynamicImage>, piece_size: (u32, u32), directory: &str, interval: usize, animation: Option<String>, frame_delay: u32) -> Result<Self, String> {
+        fs::create_dir_all(directory).map_err(|error| format!("Failed to create snapshot directory {}: {}", directory, error))?;
+
+        Ok(Self { pieces, piece_size, directory: directory.to_string(), interval, animation, frame_delay, last_snapshot: 0, frames: Vec::new() })
+    }
+
+    fn save_frame(&mut self, generation: usize, best: &[Vec<String>]) {
+        let path = Path::new(&self.directory).join(format!("{}{:05}.png", FRAME_PREFIX, generation));
+        let image_matrix = utils::create_image_matrix(best, self.pieces, self.piece_size);
+        let image = utils::create_image_from_matrix(&image_matrix);
+
+        match image.save(&path) {
+            Ok(()) => {
+                self.last_snapshot = generation;
+                self.frames.push(path);
+            }
+            Err(error) => eprintln!("Failed to save snapshot {}: {}", path.display(), error),
+        }
+    }
+
+    fn write_animation(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|error| format!("Failed to create animation {}: {}", path, error))?;
+        let mut encoder = GifEncoder::new_with_speed(file, GIF_QUANTIZATION_SPEED);
+        encoder.set_repeat(Repeat::Infinite).map_err(|error| format!("Failed to write animation {}: {}", path, error))?;
+
+        for frame_path in &self.frames {
+            let frame = image::open(frame_path).map_err(|error| format!("Failed to read snapshot {}: {}", frame_path.display(), error))?;
+            let frame = Frame::from_parts(frame.to_rgba8(), 0, 0, Delay::from_numer_denom_ms(self.frame_delay, 1));
+            encoder.encode_frame(frame).map_err(|error| format!("Failed to write animation {}: {}", path, error))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Observer for SnapshotRecorder<'_> {
+    fn on_generation_complete(&mut self, stats: &GenerationStats, best: &[Vec<String>]) {
+        if stats.generation.is_multiple_of(self.interval) {
+            self.save_frame(stats.generation, best);
+        }
+    }
+
+    fn on_termination(&mut self, _reason: &StopReason, stats: &GenerationStats, best: &[Vec<String>]) {
+        if self.last_snapshot != stats.generation {
+            self.save_frame(stats.generation, best);
+        }
+
+        if let Some(path) = &self.animation {
+            self.write_animation(path).unwrap_or_else(|error| eprintln!("{}", error));
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use std::time::Duration;
+
+use rayon::prelude::*;
+
+use crate::checkpoint::Checkpoint;
+use crate::config::Config;
+use crate::crossover::CrossoverMethod;
+use crate::diversity::DiversityStats;
+use crate::elitism::EliteSize;
+use crate::ground_truth::GroundTruth;
+use crate::init_population::SeedingRatios;
+use crate::island::{self, EvolutionContext, Island, IslandSettings};
+use crate::observer::Observer;
+use crate::problem::PreparedProblem;
+use crate::selection::SelectionStrategy;
+use crate::stats::{self, GenerationStats};
+use crate::termination::{StopReason, TerminationCriteria, TerminationState};
+
+/// Outcome of a finished run.
+pub struct RunResult {
+    pub seed: u64,
+    pub best: Vec<Vec<String>>,
+    pub stop_reason: StopReason,
+    pub stats: GenerationStats,
+}
+
+/// Configures a [`Solver`] for a [`PreparedProblem`].
+pub struct SolverBuilder<'a> {
+    problem: &'a PreparedProblem,
+    config: Config,
+    grid_size: Option<(usize, usize)>,
+    resume: Option<Checkpoint>,
+    ground_truth: Option<&'a GroundTruth>,
+    observers: Vec<Box<dyn Observer + 'a>>,
+    verbose: bool,
+}
+
+impl<'a> SolverBuilder<'a> {
+    /// Replaces every GA setting at once, e.g. with a config parsed from arguments.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Overrides the grid size derived from the original image.
+    pub fn grid_size(mut self, width: usize, height: usize) -> Self {
+        self.grid_size = Some((width, height));
+        self
+    }
+
+    pub fn population_size(mut self, population_size: usize) -> Self {
+        self.config.population_size = population_size;
+        self
+    }
+
+    pub fn islands(mut self, islands: usize) -> Self {
+        self.config.islands = islands;
+        self
+    }
+
+    pub fn selection(mut self, selection: SelectionStrategy) -> Self {
+        self.config.selection = selection;
+        self
+    }
+
+    pub fn crossover(mut self, crossover: CrossoverMethod) -> Self {
+        self.config.crossover = crossover;
+        self
+    }
+
+    pub fn elitism(mut self, elitism: EliteSize) -> Self {
+        self.config.elitism = elitism;
+        self
+    }
+
+    pub fn mutation_rate(mut self, mutation_rate: f64) -> Self {
+        self.config.mutation_rate = mutation_rate;
+        self
+    }
+
+    pub fn seeding(mut self, seeding: SeedingRatios) -> Self {
+        self.config.seeding = seeding;
+        self
+    }
+
+    pub fn termination(mut self, termination: TerminationCriteria) -> Self {
+        self.config.termination = termination;
+        self
+    }
+
+    pub fn max_generations(mut self, generations: usize) -> Self {
+        self.config.termination.max_generations = Some(generations);
+        self
+    }
+
+    pub fn time_limit(mut self, budget: Duration) -> Self {
+        self.config.termination.time_budget = Some(budget);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Continues a checkpointed run instead of creating a new population, on
+    /// the grid size the checkpoint was saved with.
+    pub fn resume(mut self, checkpoint: Checkpoint) -> Self {
+        self.resume = Some(checkpoint);
+        self
+    }
+
+    /// Reports accuracy against this ground truth in the generation stats.
+    pub fn ground_truth(mut self, ground_truth: &'a GroundTruth) -> Self {
+        self.ground_truth = Some(ground_truth);
+        self
+    }
+
+    pub fn observer<O: Observer + 'a>(mut self, observer: O) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    pub fn boxed_observer(mut self, observer: Box<dyn Observer + 'a>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Prints progress to stdout after every generation.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Creates the initial population, or restores it from the checkpoint.
+    pub fn build(self) -> Result<Solver<'a>, String> {
+        let config = self.config;
+        config.validate()?;
+
+        let seed = crate::resolve_seed(&config, self.resume.as_ref());
+        let checkpoint_grid_size = self.resume.as_ref().and_then(|checkpoint| checkpoint.grid_size);
+        if let (Some(requested), Some(saved)) = (self.grid_size, checkpoint_grid_size) {
+            if requested != saved {
+                return Err(format!("Grid size {}x{} does not match the checkpoint's {}x{}", requested.0, requested.1, saved.0, saved.1));
+            }
+        }
+        let (width, height) = self.grid_size.or(checkpoint_grid_size).unwrap_or((self.problem.width, self.problem.height));
+        if width == 0 || height == 0 {
+            return Err(format!("Grid size must be positive, got {}x{}", width, height));
+        }
+        let context = evolution_context(self.problem, &config, width, height);
+
+        let (islands, termination, generation) = match self.resume {
+            Some(checkpoint) => {
+                if self.verbose {
+                    println!("Resuming from generation {} (seed {})", checkpoint.generation, seed);
+                }
+                let islands: Vec<Island> = checkpoint.islands
+                    .into_iter()
+                    .map(|state| Island::from_state(state, &context))
+                    .collect();
+                let termination = TerminationState::resume(config.termination, checkpoint.elapsed, checkpoint.best_fitness, checkpoint.stagnant_generations);
+                (islands, termination, checkpoint.generation)
+            }
+            None => {
+                if self.verbose {
+                    println!("Using seed {}", seed);
+                }
+                let islands: Vec<Island> = (0..config.islands)
+                    .into_par_iter()
+                    .map(|id| Island::new(id, seed.wrapping_add(id as u64), IslandSettings::for_island(&config, id), &context))
+                    .collect();
+                (islands, TerminationState::new(config.termination), 0)
+            }
+        };
+
+        let best_fitness = termination.best_fitness().unwrap_or(f64::INFINITY);
+
+        Ok(Solver {
+            problem: self.problem,
+            config,
+            width,
+            height,
+            seed,
+            islands,
+            termination,
+            generation,
+            best_fitness,
+            ground_truth: self.ground_truth,
+            observers: self.observers,
+            stats: None,
+            stop_reason: None,
+            verbose: self.verbose,
+        })
+    }
+}
+
+/// A genetic algorithm run that can be advanced one generation at a time.
+pub struct Solver<'a> {
+    problem: &'a PreparedProblem,
+    config: Config,
+    width: usize,
+    height: usize,
+    seed: u64,
+    islands: Vec<Island>,
+    termination: TerminationState,
+    generation: usize,
+    best_fitness: f64,
+    ground_truth: Option<&'a GroundTruth>,
+    observers: Vec<Box<dyn Observer + 'a>>,
+    stats: Option<GenerationStats>,
+    stop_reason: Option<StopReason>,
+    verbose: bool,
+}
+
+impl<'a> Solver<'a> {
+    pub fn builder(problem: &'a PreparedProblem) -> SolverBuilder<'a> {
+        SolverBuilder {
+            problem,
+            config: Config::default(),
+            grid_size: None,
+            resume: None,
+            ground_truth: None,
+            observers: Vec::new(),
+            verbose: false,
+        }
+    }
+
+    /// Evolves every island by one generation, notifies the observers and
+    /// returns the reason to stop once a termination criterion is met.
+    pub fn step(&mut self) -> Option<StopReason> {
+        let context = evolution_context(self.problem, &self.config, self.width, self.height);
+        let config = &self.config;
+
+        self.islands.par_iter_mut().for_each(|island| island.evolve(&context));
+        self.generation += 1;
+        let generation = self.generation;
+
+        if config.migration_interval > 0 && generation.is_multiple_of(config.migration_interval) {
+            island::migrate(&mut self.islands, config.topology, config.migrants, &context);
+        }
+        self.islands.par_iter_mut().for_each(Island::measure_diversity);
+
+        let generation_stats = collect_stats(generation, &self.islands, self.ground_truth, self.termination.elapsed());
+
+        if self.verbose {
+            println!("Generation {} finished (best fitness {:.2}, {} unique chromosomes)", generation, generation_stats.best_fitness, generation_stats.unique_chromosomes);
+
+            for island in &self.islands {
+                if let Some(restart) = island.restart {
+                    println!(
+                        "Island {}: restarted {} individuals (stagnant for {} generations, adjacency diversity {:.3})",
+                        island.id, restart.count, restart.stagnant_generations, restart.adjacency_diversity
+                    );
+                }
+            }
+
+            if config.adaptive.is_enabled() {
+                for island in &self.islands {
+                    println!(
+                        "  island {}: mutation rate {:.3}, selection {:?}, elite {}",
+                        island.id, island.settings.mutation_rate, island.settings.selection, island.settings.elite_count
+                    );
+                }
+            }
+        }
+
+        let (best_chromosome, _) = fittest(&self.islands).expect("islands have no population");
+        for observer in self.observers.iter_mut() {
+            observer.on_generation_complete(&generation_stats, best_chromosome);
+        }
+
+        if generation_stats.best_fitness < self.best_fitness {
+            self.best_fitness = generation_stats.best_fitness;
+            for observer in self.observers.iter_mut() {
+                observer.on_new_best(generation, self.best_fitness, best_chromosome);
+            }
+        }
+
+        let elites_identical = self.islands.iter().all(|island| island.elites_identical());
+        let observers = &mut self.observers;
+        let stop_reason = self
+            .termination
+            .check(generation, generation_stats.best_fitness, elites_identical)
+            .or_else(|| observers.iter_mut().find_map(|observer| observer.should_stop(&generation_stats)).map(StopReason::Observer));
+
+        if let Some(path) = &config.checkpoint {
+            let interval_reached = config.checkpoint_interval > 0 && generation.is_multiple_of(config.checkpoint_interval);
+            if interval_reached || stop_reason.is_some() {
+                self.checkpoint()
+                    .save(path)
+                    .unwrap_or_else(|error| eprintln!("{}", error));
+            }
+        }
+
+        self.stats = Some(generation_stats);
+        self.stop_reason = stop_reason.clone();
+        stop_reason
+    }
+
+    /// Steps until a termination criterion is met, then finishes the run.
+    pub fn run(mut self) -> RunResult {
+        while self.stop_reason.is_none() {
+            self.step();
+        }
+        self.finish()
+    }
+
+    /// Ends the run, notifying the observers, even if no criterion was met
+    /// yet. Finishing before the first step reports the initial population.
+    pub fn finish(mut self) -> RunResult {
+        let stop_reason = self.stop_reason.take().unwrap_or(StopReason::Caller);
+        let stats = match self.stats.take() {
+            Some(stats) => stats,
+            None => {
+                self.islands.iter_mut().for_each(Island::measure_diversity);
+                collect_stats(self.generation, &self.islands, self.ground_truth, self.termination.elapsed())
+            }
+        };
+
+        if self.verbose {
+            println!("Stopped after {} generations in {:.1}s: {}", self.generation, self.termination.elapsed().as_secs_f64(), stop_reason);
+        }
+
+        let (best, _) = fittest(&self.islands).expect("islands have no population");
+        for observer in self.observers.iter_mut() {
+            observer.on_termination(&stop_reason, &stats, best);
+        }
+
+        RunResult { seed: self.seed, best: best.clone(), stop_reason, stats }
+    }
+
+    /// The fittest chromosome across all islands and its fitness.
+    pub fn best(&self) -> (&Vec<Vec<String>>, f64) {
+        fittest(&self.islands).expect("islands have no population")
+    }
+
+    /// Statistics of the last generation, `None` before the first step.
+    pub fn stats(&self) -> Option<&GenerationStats> {
+        self.stats.as_ref()
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn islands(&self) -> &[Island] {
+        &self.islands
+    }
+
+    /// Snapshot of the run that [`SolverBuilder::resume`] can continue from.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint::capture(&self.config, self.seed, (self.width, self.height), self.generation, &self.termination, &self.islands)
+    }
+}
+
+fn evolution_context<'a>(problem: &'a PreparedProblem, config: &'a Config, width: usize, height: usize) -> EvolutionContext<'a> {
+    EvolutionContext { width, height, ..problem.context(config) }
+}
+
+fn fittest(islands: &[Island]) -> Option<(&Vec<Vec<String>>, f64)> {
+    islands
+        .iter()
+        .filter_map(|island| island.best())
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+fn collect_stats(generation: usize, islands: &[Island], ground_truth: Option<&GroundTruth>, elapsed: Duration) -> GenerationStats {
+    let fitness_scores: Vec<f64> = islands.iter().flat_map(|island| island.fitness_scores().iter().map(|&(_, fitness)| fitness)).collect();
+    let fitness = stats::summarize_fitness(&fitness_scores);
+
+    let diversities: Vec<DiversityStats> = islands.iter().filter_map(|island| island.diversity).collect();
+    let adjacency_diversity = diversities.iter().map(|diversity| diversity.adjacency_diversity).sum::<f64>() / diversities.len().max(1) as f64;
+
+    let island_mean = |setting: fn(&Island) -> f64| islands.iter().map(setting).sum::<f64>() / islands.len().max(1) as f64;
+
+    let accuracy = ground_truth.zip(fittest(islands)).map(|(truth, (chromosome, _))| truth.accuracy(chromosome));
+
+    GenerationStats {
+        generation,
+        best_fitness: fitness.best,
+        mean_fitness: fitness.mean,
+        worst_fitness: fitness.worst,
+        median_fitness: fitness.median,
+        unique_chromosomes: diversities.iter().map(|diversity| diversity.unique_chromosomes).sum(),
+        adjacency_diversity,
+        crossover_fallbacks: islands.iter().map(|island| island.crossover_fallbacks).sum(),
+        mean_mutation_rate: island_mean(|island| island.settings.mutation_rate),
+        mean_selection_pressure: island_mean(|island| island.settings.selection.pressure()),
+        mean_elite_count: island_mean(|island| island.settings.elite_count as f64),
+        elapsed_seconds: elapsed.as_secs_f64(),
+        accuracy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::adaptive::AdaptivePolicy;
+    use crate::diversity;
+    use crate::problem::CostMatrixProblem;
+
+    // Nine pieces on a 3 x 3 grid with costs that only depend on the pair.
+    fn problem() -> PreparedProblem {
+        let piece_keys: Vec<String> = (0..9).map(|i| format!("piece{}", i)).collect();
+        let mut right = HashMap::new();
+        let mut down = HashMap::new();
+        for (i, first) in piece_keys.iter().enumerate() {
+            for (j, second) in piece_keys.iter().enumerate() {
+                right.insert((first.clone(), second.clone()), ((i * 5 + j * 3) % 11) as f64);
+                down.insert((first.clone(), second.clone()), ((i * 3 + j * 7) % 13) as f64);
+            }
+        }
+        PreparedProblem::new(&CostMatrixProblem { piece_keys, width: 3, height: 3, right, down, missing_cost: 50.0 }).unwrap()
+    }
+
+    fn config() -> Config {
+        Config {
+            population_size: 30,
+            islands: 2,
+            migration_interval: 1,
+            seed: Some(3),
+            termination: TerminationCriteria { max_generations: Some(5), ..TerminationCriteria::default() },
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn stats_describe_the_population_after_migration() {
+        let problem = problem();
+        let mut solver = Solver::builder(&problem).config(config()).build().unwrap();
+
+        for _ in 0..3 {
+            solver.step();
+            let measured: Vec<DiversityStats> = solver.islands().iter().map(|island| diversity::measure_diversity(&island.population)).collect();
+            let stats = solver.stats().unwrap();
+
+            assert_eq!(stats.unique_chromosomes, measured.iter().map(|diversity| diversity.unique_chromosomes).sum::<usize>());
+            let adjacency_diversity = measured.iter().map(|diversity| diversity.adjacency_diversity).sum::<f64>() / measured.len() as f64;
+            assert_eq!(stats.adjacency_diversity.to_bits(), adjacency_diversity.to_bits());
+        }
+    }
+
+    #[test]
+    fn stats_record_the_adapted_island_settings() {
+        let problem = problem();
+        let config = Config {
+            adaptive: AdaptivePolicy { stagnation_limit: 1, min_diversity: 1.0, ..AdaptivePolicy::default() },
+            island_selection: vec![SelectionStrategy::Tournament(3), SelectionStrategy::Rank(1.5)],
+            mutation_rate: 0.02,
+            ..config()
+        };
+        let mut solver = Solver::builder(&problem).config(config).build().unwrap();
+
+        for _ in 0..3 {
+            solver.step();
+            let islands = solver.islands();
+            let stats = solver.stats().unwrap();
+
+            let mean = |setting: fn(&IslandSettings) -> f64| islands.iter().map(|island| setting(&island.settings)).sum::<f64>() / islands.len() as f64;
+            assert_eq!(stats.mean_mutation_rate, mean(|settings| settings.mutation_rate));
+            assert_eq!(stats.mean_selection_pressure, mean(|settings| settings.selection.pressure()));
+            assert_eq!(stats.mean_elite_count, mean(|settings| settings.elite_count as f64));
+        }
+        assert!(solver.stats().unwrap().mean_mutation_rate > 0.02);
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::observer::Observer;
+
+const CSV_HEADER: &str = "generation,best_fitness,mean_fitness,worst_fitness,median_fitness,unique_chromosomes,adjacency_diversity,crossover_fallbacks,mean_mutation_rate,mean_selection_pressure,mean_elite_count,elapsed_seconds,accuracy";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StatsFormat {
+    JsonLines,
+    Csv,
+}
+
+impl StatsFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "jsonl" => Some(StatsFormat::JsonLines),
+            "csv" => Some(StatsFormat::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => StatsFormat::Csv,
+            _ => StatsFormat::JsonLines,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub generation: usize,
+    pub best_fitness: f64,
+    pub mean_fitness: f64,
+    pub worst_fitness: f64,
+    pub median_fitness: f64,
+    pub unique_chromosomes: usize,
+    pub adjacency_diversity: f64,
+    pub crossover_fallbacks: usize,
+    /// Island averages of the settings adaptive control may have changed;
+    /// the selection pressure is the strategy's parameter.
+    pub mean_mutation_rate: f64,
+    pub mean_selection_pressure: f64,
+    pub mean_elite_count: f64,
+    pub elapsed_seconds: f64,
+    pub accuracy: Option<f64>,
+}
+
+pub struct FitnessSummary {
+    pub best: f64,
+    pub mean: f64,
+    pub worst: f64,
+    pub median: f64,
+}
+
+pub fn summarize_fitness(fitness_scores: &[f64]) -> FitnessSummary {
+    let mut sorted = fitness_scores.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    if sorted.is_empty() {
+        return FitnessSummary { best: f64::NAN, mean: f64::NAN, worst: f64::NAN, median: f64::NAN };
+    }
+
+    let middle = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    };
+
+    FitnessSummary {
+        best: sorted[0],
+        mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        worst: sorted[sorted.len() - 1],
+        median,
+    }
+}
+
+pub struct StatsLog {
+    writer: BufWriter<File>,
+    format: StatsFormat,
+}
+
+impl StatsLog {
+    pub fn open(path: &str, format: StatsFormat, append: bool) -> Result<Self, String> {
+        let continuing = append && Path::new(path).exists();
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(continuing)
+            .truncate(!continuing)
+            .open(path)
+            .map_err(|error| format!("Failed to open statistics log {}: {}", path, error))?;
+
+        let mut log = Self { writer: BufWriter::new(file), format };
+        if format == StatsFormat::Csv && !continuing {
+            log.write_line(CSV_HEADER)?;
+        }
+
+        Ok(log)
+    }
+
+    pub fn write(&mut self, stats: &GenerationStats) -> Result<(), String> {
+        let line = match self.format {
+            StatsFormat::JsonLines => serde_json::to_string(stats).map_err(|error| format!("Failed to serialize statistics: {}", error))?,
+            StatsFormat::Csv => format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                stats.generation,
+                stats.best_fitness,
+                stats.mean_fitness,
+                stats.worst_fitness,
+                stats.median_fitness,
+                stats.unique_chromosomes,
+                stats.adjacency_diversity,
+                stats.crossover_fallbacks,
+                stats.mean_mutation_rate,
+                stats.mean_selection_pressure,
+                stats.mean_elite_count,
+                stats.elapsed_seconds,
+                stats.accuracy.map_or(String::new(), |accuracy| accuracy.to_string()),
+            ),
+        };
+
+        self.write_line(&line)?;
+        self.writer.flush().map_err(|error| format!("Failed to write statistics log: {}", error))
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.writer, "{}", line).map_err(|error| format!("Failed to write statistics log: {}", error))
+    }
+}
+
+impl Observer for StatsLog {
+    fn on_generation_complete(&mut self, stats: &GenerationStats, _best: &[Vec<String>]) {
+        self.write(stats).unwrap_or_else(|error| eprintln!("{}", error));
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct TerminationCriteria {
+    pub max_generations: Option<usize>,
+    pub time_budget: Option<Duration>,
+    pub stagnation_limit: Option<usize>,
+    pub target_fitness: Option<f64>,
+    /// Stops once every island's elites are the same chromosome. Duplicate
+    /// replacement keeps chromosomes distinct, so this needs duplicates kept.
+    pub stop_on_identical_elites: bool,
+}
+
+impl TerminationCriteria {
+    pub fn is_bounded(&self) -> bool {
+        self.has_hard_limit() || self.stop_on_identical_elites
+    }
+
+    pub fn has_hard_limit(&self) -> bool {
+        self.max_generations.is_some() || self.time_budget.is_some() || self.stagnation_limit.is_some() || self.target_fitness.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    MaxGenerations(usize),
+    TimeBudget(Duration),
+    Stagnation(usize),
+    TargetFitness(f64),
+    IdenticalElites,
+    Observer(String),
+    /// The caller finished the run before any criterion was met.
+    Caller,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::MaxGenerations(generations) => write!(f, "reached the maximum of {} generations", generations),
+            StopReason::TimeBudget(budget) => write!(f, "time budget of {:.1}s exhausted", budget.as_secs_f64()),
+            StopReason::Stagnation(generations) => write!(f, "best fitness unchanged for {} generations", generations),
+            StopReason::TargetFitness(target) => write!(f, "best fitness reached the target of {}", target),
+            StopReason::IdenticalElites => write!(f, "all elites are identical"),
+            StopReason::Observer(reason) => write!(f, "{}", reason),
+            StopReason::Caller => write!(f, "stopped by caller"),
+        }
+    }
+}
+
+pub struct TerminationState {
+    criteria: TerminationCriteria,
+    started: Instant,
+    best_fitness: Option<f64>,
+    stagnant_generations: usize,
+}
+
+impl TerminationState {
+    pub fn new(criteria: TerminationCriteria) -> Self {
+        Self { criteria, started: Instant::now(), best_fitness: None, stagnant_generations: 0 }
+    }
+
+    pub fn resume(criteria: TerminationCriteria, elapsed: Duration, best_fitness: Option<f64>, stagnant_generations: usize) -> Self {
+        let started = Instant::now().checked_sub(elapsed).unwrap_or_else(Instant::now);
+        Self { criteria, started, best_fitness, stagnant_generations }
+    }
+
+    pub fn best_fitness(&self) -> Option<f64> {
+        self.best_fitness
+    }
+
+    pub fn stagnant_generations(&self) -> usize {
+        self.stagnant_generations
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn check(&mut self, generation: usize, best_fitness: f64, elites_identical: bool) -> Option<StopReason> {
+        match self.best_fitness {
+            Some(best) if best_fitness >= best => self.stagnant_generations += 1,
+            _ => {
+                self.best_fitness = Some(best_fitness);
+                self.stagnant_generations = 0;
+            }
+        }
+
+        if let Some(target) = self.criteria.target_fitness {
+            if best_fitness <= target {
+                return Some(StopReason::TargetFitness(target));
+            }
+        }
+
+        if self.criteria.stop_on_identical_elites && elites_identical {
+            return Some(StopReason::IdenticalElites);
+        }
+
+        if let Some(limit) = self.criteria.stagnation_limit {
+            if self.stagnant_generations >= limit {
+                return Some(StopReason::Stagnation(limit));
+            }
+        }
+
+        if let Some(budget) = self.criteria.time_budget {
+            if self.started.elapsed() >= budget {
+                return Some(StopReason::TimeBudget(budget));
+            }
+        }
+
+        if let Some(max_generations) = self.criteria.max_generations {
+            if generation >= max_generations {
+                return Some(StopReason::MaxGenerations(max_generations));
+            }
+        }
+
+        None
+    }
+}
@@ -1,7 +1,8 @@
 use std::fs;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use image::{self, imageops, DynamicImage, GenericImage, GenericImageView, RgbaImage};
 use rand::seq::SliceRandom;
+use rand::Rng;
 
 pub fn build_matrix(
     positions: &HashMap<String, (i32, i32)>,
@@ -27,37 +28,39 @@ pub fn build_matrix(
     matrix
 }
 
-pub fn create_random_matrix(
-    loaded_piece_keys: &Vec<String>,
+pub fn create_random_matrix<R: Rng>(
+    loaded_piece_keys: &[String],
     matrix_width: usize,
     matrix_height: usize,
+    rng: &mut R,
 ) -> Vec<Vec<String>> {
-    let mut rng = rand::thread_rng();
-    let mut shuffled_keys = loaded_piece_keys.clone();
-    shuffled_keys.shuffle(&mut rng);
-
-    let mut matrix: Vec<Vec<String>> = vec![vec![String::new(); matrix_width]; matrix_height];
-    let mut loaded_piece_keys_iter = shuffled_keys.iter().cloned().cycle();
-
-    for i in 0..matrix_height {
-        for j in 0..matrix_width {
-            if let Some(loaded_piece_key) = loaded_piece_keys_iter.next() {
-                matrix[i][j] = loaded_piece_key.clone();
-            }
-        }
-    }
-
-    matrix
+    let mut shuffled_keys = loaded_piece_keys.to_vec();
+    shuffled_keys.shuffle(rng);
+
+    let cells = matrix_width * matrix_height;
+    shuffled_keys.resize(cells, String::new());
+    shuffled_keys.shuffle(rng);
+
+    shuffled_keys
+        .chunks(matrix_width.max(1))
+        .take(matrix_height)
+        .map(|row| row.to_vec())
+        .collect()
 }
 
 
-pub fn create_image_matrix(chromosome: &Vec<Vec<String>>, pieces: &HashMap<String, DynamicImage>) -> Vec<Vec<DynamicImage>> {
+/// Looks up the image of every placed piece; empty cells become transparent
+/// images of `cell_size`.
+pub fn create_image_matrix(chromosome: &[Vec<String>], pieces: &HashMap<String, DynamicImage>, cell_size: (u32, u32)) -> Vec<Vec<DynamicImage>> {
     let mut image_matrix: Vec<Vec<DynamicImage>> = Vec::new();
+    let (cell_width, cell_height) = cell_size;
 
     for row in chromosome {
         let mut image_row: Vec<DynamicImage> = Vec::new();
         for key in row {
-            if let Some(image) = pieces.get(key) {
+            if key.is_empty() {
+                image_row.push(DynamicImage::new_rgba8(cell_width, cell_height));
+            } else if let Some(image) = pieces.get(key) {
                 image_row.push(image.clone());
             } else {
                 panic!("Key not found in pieces hashmap: {}", key);
@@ -69,7 +72,7 @@ pub fn create_image_matrix(chromosome: &Vec<Vec<String>>, pieces: &HashMap<Strin
     image_matrix
 }
 
-pub fn create_image_from_matrix(matrix: &Vec<Vec<DynamicImage>>) -> RgbaImage {
+pub fn create_image_from_matrix(matrix: &[Vec<DynamicImage>]) -> RgbaImage {
     let mut image_width = 0;
     let mut image_height = 0;
 
@@ -98,6 +101,10 @@ pub fn create_image_from_matrix(matrix: &Vec<Vec<DynamicImage>>) -> RgbaImage {
 }
 
 pub fn calculate_dimensions_stats(images: &HashMap<String, DynamicImage>) -> Option<(u32, u32, u32, u32, u32, u32)> {
+    if images.is_empty() {
+        return None;
+    }
+
     let total_images = images.len() as u32;
     let (total_width, total_height, smallest_width,  smallest_height,largest_width, largest_height) =
         images.values().fold((0, 0, u32::MAX, u32::MAX, 0, 0), |acc, img| {
@@ -114,47 +121,55 @@ pub fn calculate_dimensions_stats(images: &HashMap<String, DynamicImage>) -> Opt
             )
         });
 
-    if total_images > 0 {
-        Some((
-            total_width / total_images,
-            total_height / total_images,
-            smallest_width,
-            smallest_height,
-            largest_width,
-            largest_height,
-        ))
-    } else {
-        None
-    }
+    Some((
+        total_width / total_images,
+        total_height / total_images,
+        smallest_width,
+        smallest_height,
+        largest_width,
+        largest_height,
+    ))
 }
 
 pub fn get_directory_contents(folder_path: &str) -> Result<Vec<String>, std::io::Error> {
     if let Ok(entries) = fs::read_dir(folder_path) {
         let mut paths = Vec::new();
 
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if let Some(path_str) = path.to_str() {
-                    paths.push(path_str.to_string());
-                }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Some(path_str) = path.to_str() {
+                paths.push(path_str.to_string());
             }
         }
 
         Ok(paths)
     } else {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
+        Err(std::io::Error::other(
             format!("Failed to read directory: {}", folder_path),
         ))
     }
 }
 
+pub fn load_pieces(folder_path: &str) -> Result<HashMap<String, DynamicImage>, String> {
+    let pieces_paths = get_directory_contents(folder_path).map_err(|error| error.to_string())?;
+    let mut loaded_pieces: HashMap<String, DynamicImage> = HashMap::new();
+    for piece in pieces_paths {
+        let current_image = image::open(&piece).map_err(|error| format!("Failed to open piece {}: {}", piece, error))?;
+        if current_image.dimensions().0 == 1 || current_image.dimensions().1 == 1 {
+            continue;
+        }
+        if let Some(file_name) = piece.rsplit('/').next() {
+            loaded_pieces.insert(file_name.to_string(), current_image);
+        }
+    }
+    Ok(loaded_pieces)
+}
+
 pub fn resize(piece: &DynamicImage, target_width: u32, target_height: u32) -> DynamicImage {
     image::DynamicImage::ImageRgba8(imageops::resize(piece, target_width, target_height, image::imageops::FilterType::Nearest))
 }
 
-pub fn find_position_in_matrix(parent1: &Vec<Vec<String>>, target: &str) -> Option<(usize, usize)> {
+pub fn find_position_in_matrix(parent1: &[Vec<String>], target: &str) -> Option<(usize, usize)> {
     for (row_idx, row) in parent1.iter().enumerate() {
         for (col_idx, value) in row.iter().enumerate() {
             if value == target {
@@ -165,8 +180,48 @@ pub fn find_position_in_matrix(parent1: &Vec<Vec<String>>, target: &str) -> Opti
     None
 }
 
-pub fn matrix_size<T>(matrix: &Vec<Vec<T>>) -> (usize, usize) {
+/// The rejection pool: pieces from `pieces` that `chromosome` leaves unplaced.
+pub fn rejected_pieces(chromosome: &[Vec<String>], pieces: &[String]) -> Vec<String> {
+    let placed: HashSet<&String> = chromosome.iter().flatten().collect();
+    pieces.iter().filter(|piece| !placed.contains(piece)).cloned().collect()
+}
+
+pub fn placed_piece_count(chromosome: &[Vec<String>]) -> usize {
+    chromosome.iter().flatten().filter(|key| !key.is_empty()).count()
+}
+
+pub fn matrix_size<T>(matrix: &[Vec<T>]) -> (usize, usize) {
     let num_rows = matrix.len();
-    let num_columns = matrix.get(0).map_or(0, |row| row.len());
+    let num_columns = matrix.first().map_or(0, |row| row.len());
     (num_rows, num_columns)
-}
\ No newline at end of file
+}
+
+pub fn opposite_relation(relation: char) -> char {
+    match relation {
+        'L' => 'R',
+        'R' => 'L',
+        'U' => 'D',
+        'D' => 'U',
+        _ => relation,
+    }
+}
+
+pub fn adjacent_cells(cell: (usize, usize), width: usize, height: usize) -> Vec<(char, (usize, usize))> {
+    let (row, col) = cell;
+    let mut cells = Vec::new();
+
+    if row > 0 {
+        cells.push(('U', (row - 1, col)));
+    }
+    if col + 1 < width {
+        cells.push(('R', (row, col + 1)));
+    }
+    if row + 1 < height {
+        cells.push(('D', (row + 1, col)));
+    }
+    if col > 0 {
+        cells.push(('L', (row, col - 1)));
+    }
+
+    cells
+}